@@ -0,0 +1,233 @@
+//! Time-based animation schedule.
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// Number of frames of the `steps` preset
+pub const STEPS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Easing {
+    /// Four equal steps; shrinks to 1/2, 1/3, 1/4, 1/5 of the radius (classic behaviour)
+    Steps,
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Cubic,
+    /// Damped oscillation settling at the end
+    Spring,
+}
+
+impl Easing {
+    /// Maps linear time `t` (0.0 - 1.0) to animation progress.
+    ///
+    /// The result is 0.0 at the start and 1.0 at the end, but may overshoot in between.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Steps => {
+                let step = ((t * STEPS as f32) as u32).min(STEPS - 1);
+                step as f32 / STEPS as f32
+            }
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+            Easing::Cubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - 4.0 * (1.0 - t).powi(3)
+                }
+            }
+            Easing::Spring => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    1.0 - (-6.0 * t).exp() * (3.0 * PI * t).cos()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Style {
+    /// Shrink towards the cursor
+    Shrink,
+    /// Expand outward from the cursor
+    Expand,
+    /// Grow and shrink `count` times
+    Pulse,
+    /// `count` concentric rings emanating at staggered offsets
+    Ripple,
+    /// Turn on and off `count` times
+    Blink,
+}
+
+/// One shape drawn in a frame.
+///
+/// Values produced by `Animation` are relative to the configured radius and line width;
+/// `scaled` turns them into pixels. The radius never exceeds 1, so overshooting easings
+/// such as `Spring` stay inside the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layer {
+    pub radius: f32,
+    pub line_width: f32,
+    pub opacity: f32,
+}
+
+impl Layer {
+    fn new(radius: f32, line_width: f32, opacity: f32) -> Self {
+        Self {
+            radius: radius.clamp(0.0, 1.0),
+            line_width: line_width.max(0.0),
+            opacity: opacity.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn scaled(&self, radius: f32, line_width: f32) -> Self {
+        Self {
+            radius: self.radius * radius,
+            line_width: self.line_width * line_width,
+            opacity: self.opacity,
+        }
+    }
+}
+
+/// Fade-out applied to the whole frame.
+#[derive(Clone)]
+pub struct Fade {
+    /// Time from the start of the animation until the fade begins
+    pub start: Duration,
+    pub easing: Easing,
+}
+
+#[derive(Clone)]
+pub struct Animation {
+    duration: Duration,
+    easing: Easing,
+    style: Style,
+    count: u32,
+    fade: Option<Fade>,
+}
+
+impl Animation {
+    pub fn new(
+        duration: Duration,
+        easing: Easing,
+        style: Style,
+        count: u32,
+        fade: Option<Fade>,
+    ) -> Self {
+        Self {
+            duration,
+            easing,
+            style,
+            count: count.max(1),
+            fade,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn fade(&self) -> Option<&Fade> {
+        self.fade.as_ref()
+    }
+
+    /// The same animation with a different style.
+    pub fn with_style(&self, style: Style) -> Self {
+        Self {
+            style,
+            ..self.clone()
+        }
+    }
+
+    /// Animation progress after `elapsed`, or `None` once the animation has finished.
+    pub fn progress(&self, elapsed: Duration) -> Option<f32> {
+        if elapsed >= self.duration {
+            return None;
+        }
+
+        let t = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        Some(self.easing.apply(t))
+    }
+
+    /// Opacity of the whole frame after `elapsed`.
+    pub fn opacity(&self, elapsed: Duration) -> f32 {
+        let Some(fade) = &self.fade else {
+            return 1.0;
+        };
+        if elapsed < fade.start || fade.start >= self.duration {
+            return 1.0;
+        }
+
+        let t = (elapsed - fade.start).as_secs_f32() / (self.duration - fade.start).as_secs_f32();
+        (1.0 - fade.easing.apply(t)).clamp(0.0, 1.0)
+    }
+
+    /// Layers to draw at the eased `progress`, from back to front.
+    pub fn layers(&self, progress: f32) -> Vec<Layer> {
+        let count = self.count as f32;
+        match self.style {
+            Style::Shrink => {
+                let radius = match self.easing {
+                    // Classic sizes: 1/2, 1/3, 1/4, 1/5
+                    Easing::Steps => 1.0 / (progress * STEPS as f32 + 2.0),
+                    _ => 1.0 - progress,
+                };
+                vec![Layer::new(radius, 1.0, 1.0)]
+            }
+            Style::Expand => vec![Layer::new(progress, 1.0 - progress / 2.0, 1.0)],
+            Style::Pulse => {
+                let phase = (PI * count * progress).sin();
+                vec![Layer::new(1.0 - phase * phase / 2.0, 1.0, 1.0)]
+            }
+            Style::Ripple => {
+                // Each ring lives for `span` and the next one starts halfway through it,
+                // so the last ring ends exactly at the end of the animation.
+                let span = 2.0 / (count + 1.0);
+                (0..self.count)
+                    .filter_map(|i| {
+                        let local = (progress - i as f32 * span / 2.0) / span;
+                        (0.0..1.0)
+                            .contains(&local)
+                            .then(|| Layer::new(local, 1.0 - local / 2.0, 1.0 - local))
+                    })
+                    .collect()
+            }
+            Style::Blink => {
+                let on = ((progress * count * 2.0) as u32).is_multiple_of(2);
+                vec![Layer::new(1.0, 1.0, if on { 1.0 } else { 0.0 })]
+            }
+        }
+    }
+
+    /// Time between frames. `refresh` is the display's frame period.
+    ///
+    /// The steps preset only needs a frame per step; everything else is redrawn
+    /// as often as the display refreshes.
+    pub fn frame_interval(&self, refresh: Duration) -> Duration {
+        match self.easing {
+            Easing::Steps => self.duration / STEPS,
+            _ => refresh,
+        }
+    }
+
+    /// Elapsed time of every frame of one pass, `interval` apart.
+    pub fn frame_times(&self, interval: Duration) -> impl Iterator<Item = Duration> + use<> {
+        let duration = self.duration;
+        (0..)
+            .map(move |i| interval * i)
+            .take_while(move |&elapsed| elapsed < duration)
+    }
+}
@@ -0,0 +1,65 @@
+//! Premultiplied 0xAARRGGBB pixel helpers.
+//!
+//! X11 ARGB visuals (and therefore softbuffer) expect the color channels to be
+//! premultiplied by alpha, so every pixel written to a buffer goes through here.
+
+use csscolorparser::Color;
+
+pub const TRANSPARENT: u32 = 0x00000000;
+
+/// Converts a CSS color into a premultiplied ARGB pixel.
+pub fn from_color(color: &Color) -> u32 {
+    let [r, g, b, a] = color.to_rgba8();
+    let a = a as u32;
+    let premultiply = |c: u8| (c as u32 * a + 127) / 255;
+    a << 24 | premultiply(r) << 16 | premultiply(g) << 8 | premultiply(b)
+}
+
+/// Converts a premultiplied pixel into straight RGBA bytes, as stored in image files.
+pub fn to_rgba8(argb: u32) -> [u8; 4] {
+    let a = argb >> 24;
+    if a == 0 {
+        return [0; 4];
+    }
+    let unpremultiply = |shift: u32| ((((argb >> shift) & 0xff) * 255 + a / 2) / a).min(0xff);
+    [
+        unpremultiply(16) as u8,
+        unpremultiply(8) as u8,
+        unpremultiply(0) as u8,
+        a as u8,
+    ]
+}
+
+/// Scales all channels of a premultiplied pixel by `factor` (0.0 - 1.0).
+pub fn scale(argb: u32, factor: f32) -> u32 {
+    if factor <= 0.0 {
+        return TRANSPARENT;
+    }
+    if factor >= 1.0 {
+        return argb;
+    }
+
+    let channel = |shift: u32| {
+        let c = ((argb >> shift) & 0xff) as f32;
+        ((c * factor).round() as u32).min(0xff) << shift
+    };
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+/// Composites premultiplied `src` over premultiplied `dst`.
+pub fn over(src: u32, dst: u32) -> u32 {
+    let inv_alpha = 255 - (src >> 24);
+    if inv_alpha == 255 {
+        return dst;
+    }
+    if inv_alpha == 0 {
+        return src;
+    }
+
+    let channel = |shift: u32| {
+        let s = (src >> shift) & 0xff;
+        let d = (dst >> shift) & 0xff;
+        (s + (d * inv_alpha + 127) / 255).min(0xff) << shift
+    };
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
@@ -0,0 +1,233 @@
+//! A single animated beacon window.
+//!
+//! `App` owns any number of beacons at once, e.g. overlapping click rings.
+
+use crate::animation::{Animation, Layer};
+use crate::error::Error;
+use crate::monitor::{self, MonitorInfo};
+use crate::overlay::OverlayWindow;
+use crate::raster::Frame;
+use crate::shape::Shape;
+use crate::xserver::XServer;
+use crate::{DEFAULT_REFRESH, Mode, Settings};
+use log::debug;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use winit::event_loop::ActiveEventLoop;
+use winit::monitor::MonitorHandle;
+use winit::window::WindowId;
+
+/// What a beacon draws and how it moves.
+pub struct Appearance {
+    pub mode: Mode,
+    pub animation: Animation,
+    pub color_argb: u32,
+    pub edge_color_argb: u32,
+    pub follow_cursor: bool,
+}
+
+/// The monitor containing `position`, or the primary monitor if none does.
+fn monitor_at(event_loop: &ActiveEventLoop, position: (i32, i32)) -> Option<MonitorHandle> {
+    let contains = |monitor: &MonitorHandle| {
+        let p = monitor.position();
+        let s = monitor.size();
+        (p.x..p.x + s.width as i32).contains(&position.0)
+            && (p.y..p.y + s.height as i32).contains(&position.1)
+    };
+
+    let monitor = event_loop.available_monitors().find(contains);
+    if monitor.is_none() {
+        debug!(
+            "No monitor contains {:?}, using the primary monitor",
+            position
+        );
+    }
+    monitor.or_else(|| event_loop.primary_monitor())
+}
+
+pub struct Beacon {
+    appearance: Appearance,
+    window: OverlayWindow,
+    win_position: (i32, i32),
+    /// Whether the window moves with the cursor (or the center moves within the window)
+    window_follows_cursor: bool,
+
+    radius_value: u32,
+    line_width_value: u32,
+    /// Cursor position relative to the window
+    center: (i32, i32),
+
+    frame_interval: Duration,
+    start: Instant,
+    next_update: Instant,
+    /// Eased animation progress of the current frame
+    progress: f32,
+    /// Opacity of the current frame
+    opacity: f32,
+    /// Times the animation is played again after the current pass
+    repeats: u32,
+}
+
+impl Beacon {
+    /// Opens a beacon window at `position` and starts its animation.
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        settings: &Settings,
+        server: Option<&Rc<XServer>>,
+        position: (i32, i32),
+        appearance: Appearance,
+    ) -> Result<Self, Error> {
+        let monitor = monitor_at(event_loop, position);
+        let monitor_info = monitor
+            .as_ref()
+            .map(|monitor| MonitorInfo::new(monitor, settings.needs_size_mm()));
+        debug!(
+            "Monitor: name={:?}, info={:?}",
+            monitor.as_ref().and_then(MonitorHandle::name),
+            monitor_info
+        );
+
+        let (win_position, win_size, follows_cursor) = match (&appearance.mode, &monitor) {
+            (Mode::Spotlight(_) | Mode::Crosshair, Some(monitor)) => {
+                let p = monitor.position();
+                let s = monitor.size();
+                ((p.x, p.y), (s.width, s.height), false)
+            }
+            _ => {
+                let win_size = settings.radius(monitor_info.as_ref()) * 2;
+                let half = (win_size / 2) as i32;
+                (
+                    (position.0 - half, position.1 - half),
+                    (win_size, win_size),
+                    true,
+                )
+            }
+        };
+        debug!("Window: position={:?}, size={:?}", win_position, win_size);
+
+        let window = OverlayWindow::new(
+            event_loop,
+            server,
+            win_position,
+            win_size,
+            settings.transparency(),
+        )?;
+
+        let refresh = monitor
+            .as_ref()
+            .and_then(monitor::refresh_period)
+            .unwrap_or(DEFAULT_REFRESH);
+        let frame_interval = appearance.animation.frame_interval(refresh);
+        let start = Instant::now();
+
+        Ok(Self {
+            window,
+            win_position,
+            window_follows_cursor: follows_cursor,
+            radius_value: settings.radius(monitor_info.as_ref()),
+            line_width_value: settings.line_width(monitor_info.as_ref()),
+            center: (position.0 - win_position.0, position.1 - win_position.1),
+            frame_interval,
+            start,
+            next_update: start,
+            progress: 0.0,
+            opacity: 1.0,
+            repeats: 0,
+            appearance,
+        })
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub fn next_update(&self) -> Instant {
+        self.next_update
+    }
+
+    /// Whether a redraw requested by `update` is left to the caller.
+    pub fn take_pending_redraw(&self) -> bool {
+        self.window.take_pending_redraw()
+    }
+
+    /// Whether `update` wants the current cursor position.
+    pub fn follows_cursor(&self) -> bool {
+        self.appearance.follow_cursor
+    }
+
+    /// Plays the animation once more after the current pass.
+    pub fn extend(&mut self) {
+        self.repeats += 1;
+    }
+
+    /// Advances the animation to `now` and requests a redraw.
+    /// Returns false once the animation has finished.
+    pub fn update(&mut self, now: Instant, cursor_position: Option<(i32, i32)>) -> bool {
+        let duration = self.appearance.animation.duration();
+        if now - self.start >= duration && self.repeats > 0 {
+            self.repeats -= 1;
+            self.start += duration;
+        }
+
+        let elapsed = now - self.start;
+        let Some(progress) = self.appearance.animation.progress(elapsed) else {
+            return false;
+        };
+        self.progress = progress;
+        self.opacity = self.appearance.animation.opacity(elapsed);
+
+        if let Some(cursor_position) = cursor_position {
+            self.follow_cursor(cursor_position);
+        }
+
+        self.window.request_redraw();
+        self.next_update = now + self.frame_interval;
+        true
+    }
+
+    /// Keeps the beacon centered on `cursor_position`.
+    fn follow_cursor(&mut self, cursor_position: (i32, i32)) {
+        if self.window_follows_cursor {
+            let win_position = (
+                cursor_position.0 - self.center.0,
+                cursor_position.1 - self.center.1,
+            );
+            if win_position != self.win_position {
+                debug!("Move window: position={:?}", win_position);
+                self.window.set_position(win_position);
+                self.win_position = win_position;
+            }
+        } else {
+            self.center = (
+                cursor_position.0 - self.win_position.0,
+                cursor_position.1 - self.win_position.1,
+            );
+        }
+    }
+
+    pub fn draw(&mut self, shape: &dyn Shape) -> Result<(), Error> {
+        let layers: Vec<Layer> = self
+            .appearance
+            .animation
+            .layers(self.progress)
+            .iter()
+            .map(|layer| layer.scaled(self.radius_value as f32, self.line_width_value as f32))
+            .collect();
+        debug!(
+            "Frame: progress={}, opacity={}, layers={:?}",
+            self.progress, self.opacity, layers
+        );
+
+        let frame = Frame {
+            mode: &self.appearance.mode,
+            shape,
+            layers: &layers,
+            opacity: self.opacity,
+            color_argb: self.appearance.color_argb,
+            edge_color_argb: self.appearance.edge_color_argb,
+            center: self.center,
+            radius: self.radius_value,
+        };
+        self.window.draw(|buffer| buffer.draw_frame(&frame))
+    }
+}
@@ -0,0 +1,72 @@
+//! Click visualization: reports mouse button presses and releases to the event loop.
+
+use crate::daemon::Request;
+use device_query::{DeviceQuery, DeviceState};
+use log::{debug, error, info};
+use std::time::Duration;
+use winit::event_loop::EventLoopProxy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+}
+
+impl Button {
+    /// Index into `MouseState::button_pressed`
+    fn index(self) -> usize {
+        match self {
+            Button::Left => 1,
+            Button::Middle => 2,
+            Button::Right => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Click {
+    pub button: Button,
+    /// Pressed or released
+    pub pressed: bool,
+    pub position: (i32, i32),
+}
+
+/// Polls the mouse buttons forever, sending a `Request::Click` for every change.
+pub fn watch(sample_interval: Duration, proxy: EventLoopProxy<Request>) {
+    info!("Watch for clicks: sample_interval={:?}", sample_interval);
+
+    let Some(device_state) = DeviceState::checked_new() else {
+        error!("Cannot read the mouse buttons: cannot open the X display");
+        return;
+    };
+    let mut last_pressed = device_state.get_mouse().button_pressed;
+
+    loop {
+        let mouse = device_state.get_mouse();
+
+        for button in [Button::Left, Button::Middle, Button::Right] {
+            let pressed = mouse.button_pressed.get(button.index()).copied();
+            let was_pressed = last_pressed.get(button.index()).copied();
+            let (Some(pressed), Some(was_pressed)) = (pressed, was_pressed) else {
+                continue;
+            };
+            if pressed == was_pressed {
+                continue;
+            }
+
+            let click = Click {
+                button,
+                pressed,
+                position: mouse.coords,
+            };
+            debug!("Click: {:?}", click);
+            if proxy.send_event(Request::Click(click)).is_err() {
+                return;
+            }
+        }
+
+        last_pressed = mouse.button_pressed;
+        std::thread::sleep(sample_interval);
+    }
+}
@@ -0,0 +1,227 @@
+//! Configuration file.
+//!
+//! `$XDG_CONFIG_HOME/cursor-beacon/config.toml` holds the same options as the command line,
+//! keyed by their long names:
+//!
+//! ```toml
+//! radius = "80lp"
+//! color = "deepskyblue"
+//!
+//! [profile.presentation]
+//! mode = "spotlight"
+//! fixed-position = true
+//! ```
+//!
+//! Top-level keys apply to every run, a `[profile.<name>]` table selected with `--profile`
+//! overrides them, and explicit command-line flags override both.
+
+use std::path::{Path, PathBuf};
+
+const PROFILE_TABLE: &str = "profile";
+
+/// Options that only make sense on the command line
+const RESERVED_KEYS: [&str; 6] = [
+    "config",
+    "profile",
+    "print-config",
+    "check",
+    "help",
+    "version",
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    UnknownKey(PathBuf, String),
+    InvalidValue(PathBuf, String, String),
+    UnknownProfile(String),
+    /// `--profile` without a configuration file
+    NoFile,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "cannot parse {}: {}", path.display(), e),
+            ConfigError::UnknownKey(path, key) => {
+                write!(f, "{}: unknown configuration key {:?}", path.display(), key)
+            }
+            ConfigError::InvalidValue(path, key, reason) => {
+                write!(
+                    f,
+                    "{}: invalid value for {:?}: {}",
+                    path.display(),
+                    key,
+                    reason
+                )
+            }
+            ConfigError::UnknownProfile(name) => write!(f, "unknown profile {:?}", name),
+            ConfigError::NoFile => write!(f, "--profile requires a configuration file"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// `$XDG_CONFIG_HOME/cursor-beacon/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("cursor-beacon").join("config.toml"))
+}
+
+/// Reads the configuration at `path` and turns it into command-line arguments for `command`.
+///
+/// The arguments are meant to be placed before the real ones, so explicit flags win.
+pub fn load(
+    path: &Path,
+    profile: Option<&str>,
+    command: &clap::Command,
+) -> Result<Vec<String>, ConfigError> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+    let mut table = text
+        .parse::<toml::Table>()
+        .map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+
+    let profiles = match table.remove(PROFILE_TABLE) {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => return Err(expected_table(path, PROFILE_TABLE)),
+        None => toml::Table::new(),
+    };
+    let mut args = to_args(path, "", &table, command)?;
+
+    // Every profile is checked, not only the selected one, so mistakes show up early.
+    let mut profile_args = Vec::new();
+    for (name, profile_table) in &profiles {
+        let prefix = format!("{}.{}.", PROFILE_TABLE, name);
+        let profile_table = profile_table
+            .as_table()
+            .ok_or_else(|| expected_table(path, prefix.trim_end_matches('.')))?;
+        let table_args = to_args(path, &prefix, profile_table, command)?;
+        if profile == Some(name.as_str()) {
+            profile_args = table_args;
+        }
+    }
+
+    if let Some(name) = profile {
+        if !profiles.contains_key(name) {
+            return Err(ConfigError::UnknownProfile(name.to_string()));
+        }
+        args.extend(profile_args);
+    }
+
+    Ok(args)
+}
+
+fn expected_table(path: &Path, key: &str) -> ConfigError {
+    ConfigError::InvalidValue(
+        path.to_path_buf(),
+        key.to_string(),
+        "expected a table".to_string(),
+    )
+}
+
+/// The effective value of every configurable option, keyed like the configuration file.
+pub fn to_table(matches: &clap::ArgMatches, command: &clap::Command) -> toml::Table {
+    let mut table = toml::Table::new();
+
+    for arg in command.get_arguments() {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if RESERVED_KEYS.contains(&long) {
+            continue;
+        }
+        let Some(raw) = matches
+            .get_raw(arg.get_id().as_str())
+            .and_then(|mut values| values.next_back())
+        else {
+            continue;
+        };
+
+        let raw = raw.to_string_lossy();
+        let value = if let Ok(b) = raw.parse::<bool>() {
+            toml::Value::Boolean(b)
+        } else if let Ok(i) = raw.parse::<i64>() {
+            toml::Value::Integer(i)
+        } else if let Ok(f) = raw.parse::<f64>() {
+            toml::Value::Float(f)
+        } else {
+            toml::Value::String(raw.into_owned())
+        };
+        table.insert(long.to_string(), value);
+    }
+
+    table
+}
+
+/// Turns the options of `table` into arguments, checking each value with `command`.
+///
+/// `prefix` is prepended to the keys in errors, e.g. `profile.presentation.`.
+fn to_args(
+    path: &Path,
+    prefix: &str,
+    table: &toml::Table,
+    command: &clap::Command,
+) -> Result<Vec<String>, ConfigError> {
+    let mut args = Vec::new();
+
+    for (key, value) in table {
+        let invalid = |reason: String| {
+            ConfigError::InvalidValue(path.to_path_buf(), format!("{}{}", prefix, key), reason)
+        };
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .filter(|_| !RESERVED_KEYS.contains(&long.as_str()))
+            .ok_or_else(|| {
+                ConfigError::UnknownKey(path.to_path_buf(), format!("{}{}", prefix, key))
+            })?;
+        let flag = format!("--{}", long);
+
+        let arg = if !arg.get_action().takes_values() {
+            match value {
+                toml::Value::Boolean(true) => flag,
+                toml::Value::Boolean(false) => continue,
+                _ => return Err(invalid("expected true or false".to_string())),
+            }
+        } else {
+            match value {
+                toml::Value::String(s) => format!("{}={}", flag, s),
+                toml::Value::Integer(i) => format!("{}={}", flag, i),
+                toml::Value::Float(f) => format!("{}={}", flag, f),
+                _ => return Err(invalid("expected a string or a number".to_string())),
+            }
+        };
+
+        // Parse the argument alone, so a bad value is reported against its key.
+        if let Err(e) = command
+            .clone()
+            .try_get_matches_from([command.get_name(), arg.as_str()])
+        {
+            return Err(invalid(clap_reason(&e)));
+        }
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+/// Why clap rejected a value, without the command-line wording.
+fn clap_reason(e: &clap::Error) -> String {
+    use clap::error::{ContextKind, ContextValue};
+
+    if let Some(source) = std::error::Error::source(e) {
+        return source.to_string();
+    }
+    match e.get(ContextKind::ValidValue) {
+        Some(ContextValue::Strings(values)) => format!("expected one of {}", values.join(", ")),
+        _ => e.kind().as_str().unwrap_or("rejected").to_string(),
+    }
+}
@@ -0,0 +1,265 @@
+//! Where the cursor position comes from.
+
+use device_query::{DeviceQuery, DeviceState};
+use log::{debug, info, warn};
+use std::path::Path;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::Event;
+use x11rb::protocol::xinput::{ConnectionExt as _, Device, EventMask, XIEventMask};
+use x11rb::protocol::xproto::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+
+pub trait CursorSource {
+    /// Cursor position on the screen, or None if it cannot be read.
+    fn position(&mut self) -> Option<(i32, i32)>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CursorSourceKind {
+    /// Poll through the device_query crate
+    DeviceQuery,
+    /// Ask the X server with QueryPointer
+    QueryPointer,
+    /// QueryPointer only after XInput2 raw motion events
+    Xinput2,
+    /// Replay the positions of `--cursor-script`
+    Script,
+}
+
+/// How to open a cursor source.
+///
+/// Sources are opened by the thread that uses them, since not all of them can be sent.
+#[derive(Debug, Clone)]
+pub enum CursorConfig {
+    DeviceQuery,
+    QueryPointer,
+    Xinput2,
+    Script(Vec<(i32, i32)>),
+}
+
+impl CursorConfig {
+    /// Reads the script of `Script` from `script`, one `x y` pair per line.
+    pub fn new(
+        kind: CursorSourceKind,
+        script: Option<&Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match kind {
+            CursorSourceKind::DeviceQuery => CursorConfig::DeviceQuery,
+            CursorSourceKind::QueryPointer => CursorConfig::QueryPointer,
+            CursorSourceKind::Xinput2 => CursorConfig::Xinput2,
+            CursorSourceKind::Script => {
+                let path = script.ok_or("the script source requires --cursor-script")?;
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+                CursorConfig::Script(parse_script(&text)?)
+            }
+        })
+    }
+
+    pub fn open(&self) -> Result<Box<dyn CursorSource>, Box<dyn std::error::Error>> {
+        info!("Cursor source: {:?}", self);
+        Ok(match self {
+            // `DeviceState::new` panics without a display.
+            CursorConfig::DeviceQuery => {
+                Box::new(DeviceState::checked_new().ok_or("cannot open the X display")?)
+            }
+            CursorConfig::QueryPointer => Box::new(QueryPointer::new()?),
+            CursorConfig::Xinput2 => Box::new(RawMotion::new()?),
+            CursorConfig::Script(positions) => Box::new(Script::new(positions.clone())),
+        })
+    }
+}
+
+fn parse_script(text: &str) -> Result<Vec<(i32, i32)>, String> {
+    let positions = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            let mut numbers = line.split_whitespace().map(str::parse::<i32>);
+            match (numbers.next(), numbers.next(), numbers.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+                _ => Err(format!("line {}: expected \"x y\", got {:?}", i + 1, line)),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if positions.is_empty() {
+        return Err("the cursor script has no positions".to_string());
+    }
+    Ok(positions)
+}
+
+impl CursorSource for DeviceState {
+    fn position(&mut self) -> Option<(i32, i32)> {
+        Some(self.get_mouse().coords)
+    }
+}
+
+/// Asks the X server on every call.
+pub struct QueryPointer {
+    conn: RustConnection,
+    root: u32,
+}
+
+impl QueryPointer {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        Ok(Self { conn, root })
+    }
+}
+
+impl CursorSource for QueryPointer {
+    fn position(&mut self) -> Option<(i32, i32)> {
+        let reply = self
+            .conn
+            .query_pointer(self.root)
+            .map_err(ReplyError::from)
+            .and_then(|cookie| cookie.reply());
+        match reply {
+            Ok(reply) => Some((reply.root_x.into(), reply.root_y.into())),
+            Err(e) => {
+                warn!("QueryPointer failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Asks the X server only after the pointer has moved, as reported by XInput2 raw motion events.
+pub struct RawMotion {
+    query: QueryPointer,
+    position: Option<(i32, i32)>,
+}
+
+impl RawMotion {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut query = QueryPointer::new()?;
+        query.conn.xinput_xi_query_version(2, 0)?.reply()?;
+        // Raw events are only reported to the root window.
+        query
+            .conn
+            .xinput_xi_select_events(
+                query.root,
+                &[EventMask {
+                    deviceid: Device::ALL_MASTER.into(),
+                    mask: vec![XIEventMask::RAW_MOTION],
+                }],
+            )?
+            .check()?;
+        let position = query.position();
+        Ok(Self { query, position })
+    }
+}
+
+impl CursorSource for RawMotion {
+    fn position(&mut self) -> Option<(i32, i32)> {
+        let mut moved = false;
+        loop {
+            match self.query.conn.poll_for_event() {
+                Ok(Some(Event::XinputRawMotion(_))) => moved = true,
+                Ok(Some(_)) => (),
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Reading XInput2 events failed: {}", e);
+                    return None;
+                }
+            }
+        }
+
+        if moved || self.position.is_none() {
+            self.position = self.query.position();
+        }
+        self.position
+    }
+}
+
+/// Replays recorded positions, one per call, then stays at the last one.
+pub struct Script {
+    positions: Vec<(i32, i32)>,
+    next: usize,
+}
+
+impl Script {
+    pub fn new(positions: Vec<(i32, i32)>) -> Self {
+        Self { positions, next: 0 }
+    }
+}
+
+impl CursorSource for Script {
+    fn position(&mut self) -> Option<(i32, i32)> {
+        let position = self
+            .positions
+            .get(self.next)
+            .or_else(|| self.positions.last())
+            .copied();
+        self.next += 1;
+        debug!("Scripted cursor: {:?}", position);
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shake::{ShakeConfig, ShakeDetector};
+    use std::time::Duration;
+
+    #[test]
+    fn script_skips_comments_and_blank_lines() {
+        let text = "# recorded pointer\n10 20\n\n  # indented comment\n-5 7\n   \n";
+        assert_eq!(parse_script(text), Ok(vec![(10, 20), (-5, 7)]));
+    }
+
+    #[test]
+    fn script_reports_the_malformed_line() {
+        let error = parse_script("# header\n10 20\n30\n").unwrap_err();
+        assert!(error.starts_with("line 3:"), "{}", error);
+        let error = parse_script("1 2 3").unwrap_err();
+        assert!(error.starts_with("line 1:"), "{}", error);
+        let error = parse_script("1 two").unwrap_err();
+        assert!(error.starts_with("line 1:"), "{}", error);
+    }
+
+    #[test]
+    fn empty_script_is_an_error() {
+        assert!(parse_script("").is_err());
+        assert!(parse_script("# only a comment\n\n").is_err());
+    }
+
+    #[test]
+    fn script_replays_then_stays_on_the_last_position() {
+        let mut script = Script::new(vec![(1, 2), (3, 4), (5, 6)]);
+        let positions: Vec<_> = (0..5).map(|_| script.position()).collect();
+        assert_eq!(
+            positions,
+            [(1, 2), (3, 4), (5, 6), (5, 6), (5, 6)].map(Some)
+        );
+    }
+
+    #[test]
+    fn scripted_shake_is_detected() {
+        let text = "# shake between x = 100 and 200\n\
+                    100 300\n150 300\n200 300\n150 300\n100 300\n150 300\n\
+                    200 300\n150 300\n100 300\n150 300\n200 300\n";
+        let mut cursor: Box<dyn CursorSource> = Box::new(Script::new(parse_script(text).unwrap()));
+        let mut detector = ShakeDetector::new(ShakeConfig {
+            sample_interval: Duration::from_millis(10),
+            window: Duration::from_millis(600),
+            reversals: 4,
+            min_distance: 40,
+            cooldown: Duration::from_millis(1000),
+        });
+
+        // Samples past the end of the script repeat the last position and keep still.
+        let detections: Vec<usize> = (0..30)
+            .filter(|&i| {
+                let position = cursor.position().unwrap();
+                detector.feed(Duration::from_millis(i as u64 * 10), position)
+            })
+            .collect();
+        assert_eq!(detections, vec![9]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Unix-socket control channel of the running instance.
+//!
+//! `cursor-beacon daemon` and a plain `cursor-beacon` that is currently animating listen on it;
+//! `cursor-beacon trigger` and repeated plain invocations send to it.
+
+use crate::click::Click;
+use log::{debug, info, warn};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use winit::event_loop::EventLoopProxy;
+
+/// Times a plain invocation tries to bind or hand over before giving up
+const HANDOVER_ATTEMPTS: u32 = 3;
+
+/// Request to the event loop.
+///
+/// `Show` and `Extend` are also sent from clients to the running instance, one per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    /// Show the beacon at the current cursor position, restarting a running animation
+    Show,
+    /// Play a running animation once more, or show the beacon if none is running
+    Extend,
+    /// Show a click ring (click mode only)
+    Click(Click),
+}
+
+impl std::str::FromStr for Request {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "show" => Ok(Request::Show),
+            "extend" => Ok(Request::Extend),
+            other => Err(format!("unknown request: {:?}", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Request::Show => write!(f, "show"),
+            Request::Extend => write!(f, "extend"),
+            Request::Click(click) => write!(f, "click {:?}", click),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/cursor-beacon.sock`, or the same name in a private directory
+/// `cursor-beacon-<uid>` of the temporary directory.
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => private_dir()?,
+    };
+    Ok(dir.join("cursor-beacon.sock"))
+}
+
+/// The user id of this process, as the owner of its `/proc` entry.
+fn current_uid() -> std::io::Result<u32> {
+    Ok(std::fs::metadata("/proc/self")?.uid())
+}
+
+/// Creates `cursor-beacon-<uid>` with mode 0700 in the temporary directory, or checks
+/// that the existing one belongs to this user and nobody else can enter it.
+fn private_dir() -> std::io::Result<PathBuf> {
+    let uid = current_uid()?;
+    let dir = std::env::temp_dir().join(format!("cursor-beacon-{}", uid));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => return Ok(dir),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => (),
+        Err(e) => return Err(e),
+    }
+
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} is not a private directory of this user", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Fails unless the file at `path` belongs to this user.
+fn check_owner(path: &Path) -> std::io::Result<()> {
+    if std::fs::symlink_metadata(path)?.uid() != current_uid()? {
+        return Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Bound socket file, removed when dropped so that clients stop connecting.
+pub struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        debug!("Remove socket: {}", self.0.display());
+        let _lock = lock(&self.0);
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            warn!("Cannot remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Takes the exclusive lock guarding the socket at `path`, released when the file is closed.
+///
+/// The lock file next to the socket is never removed, so everybody locks the same file.
+fn lock(path: &Path) -> std::io::Result<std::fs::File> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Binds the socket, replacing a stale one left behind by a crashed instance.
+///
+/// Fails with `AddrInUse` if another instance listens on it.
+pub fn bind() -> std::io::Result<(UnixListener, SocketFile)> {
+    let path = socket_path()?;
+
+    // Held until bound, so two instances cannot both take the socket for stale and one
+    // remove what the other just bound.
+    let _lock = lock(&path)?;
+
+    if path.symlink_metadata().is_ok() {
+        check_owner(&path)?;
+        if UnixStream::connect(&path).is_ok() {
+            return Err(std::io::Error::new(
+                ErrorKind::AddrInUse,
+                format!(
+                    "another instance is already listening at {}",
+                    path.display()
+                ),
+            ));
+        }
+        debug!("Remove stale socket: {}", path.display());
+        std::fs::remove_file(&path)?;
+    }
+
+    info!("Listen: {}", path.display());
+    let listener = UnixListener::bind(&path)?;
+    Ok((listener, SocketFile(path)))
+}
+
+/// Binds the socket as the single-instance lock, or sends `request` to the instance holding it.
+///
+/// Returns None once the request is handed over.
+pub fn bind_or_send(request: Request) -> std::io::Result<Option<(UnixListener, SocketFile)>> {
+    // An exiting instance removes its socket first, so binding succeeds on the next attempt.
+    for _ in 0..HANDOVER_ATTEMPTS {
+        match bind() {
+            Ok(bound) => return Ok(Some(bound)),
+            Err(e) if e.kind() == ErrorKind::AddrInUse => match send(request) {
+                Ok(()) => return Ok(None),
+                Err(e) => debug!("Hand over failed: {}", e),
+            },
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::new(
+        ErrorKind::AddrInUse,
+        "the running instance neither accepts requests nor exits",
+    ))
+}
+
+/// Forwards requests from clients to the event loop until the loop is gone.
+pub fn serve(listener: UnixListener, proxy: EventLoopProxy<Request>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let mut line = String::new();
+        if let Err(e) = BufReader::new(&stream).read_line(&mut line) {
+            warn!("Read failed: {}", e);
+            continue;
+        }
+        // Liveness probe of `bind`
+        if line.is_empty() {
+            continue;
+        }
+
+        match line.parse::<Request>() {
+            Ok(request) => {
+                debug!("Request: {}", request);
+                if proxy.send_event(request).is_err() {
+                    return;
+                }
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// Sends `request` to the running instance.
+pub fn send(request: Request) -> std::io::Result<()> {
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("cannot connect to {}: {}", path.display(), e),
+        )
+    })?;
+    writeln!(stream, "{}", request)
+}
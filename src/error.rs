@@ -0,0 +1,121 @@
+//! Errors that end the program, each with its own exit status.
+
+use crate::config::ConfigError;
+use log::debug;
+use std::io::IsTerminal;
+use std::os::unix::net::UnixDatagram;
+use std::process::ExitCode;
+
+/// Native protocol socket of systemd-journald
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+#[derive(Debug)]
+pub enum Error {
+    /// `--check` found an error in the configuration
+    Check,
+    /// Invalid configuration file or profile
+    Config(ConfigError),
+    /// The display cannot be opened or the event loop failed
+    Display(winit::error::EventLoopError),
+    /// A beacon window cannot be created
+    Window(Box<dyn std::error::Error>),
+    /// A beacon window cannot be drawn to, e.g. without an ARGB visual
+    Surface(Box<dyn std::error::Error>),
+    /// The control socket of the running instance failed
+    Socket(std::io::Error),
+    /// `--print-config` cannot serialize the configuration
+    Output(Box<dyn std::error::Error>),
+    /// The cursor source cannot be opened
+    Cursor(Box<dyn std::error::Error>),
+    /// `render` cannot write the images
+    Render(Box<dyn std::error::Error>),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Error::Check => 1,
+            // Same as clap's usage errors
+            Error::Config(_) => 2,
+            Error::Display(_) => 3,
+            Error::Window(_) => 4,
+            Error::Surface(_) => 5,
+            Error::Socket(_) => 6,
+            Error::Output(_) => 7,
+            Error::Cursor(_) => 8,
+            Error::Render(_) => 9,
+        })
+    }
+
+    /// Prints the error, also sending it to the systemd journal when nobody watches stderr
+    /// (e.g. started from a hotkey).
+    pub fn report(&self) {
+        eprintln!("error: {}", self);
+
+        if !std::io::stderr().is_terminal()
+            && let Err(e) = journal(&self.to_string())
+        {
+            debug!("Cannot log to the journal: {}", e);
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Check => write!(f, "the configuration has errors"),
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Display(e) => write!(f, "cannot run on the display: {}", e),
+            Error::Window(e) => write!(f, "cannot create a window: {}", e),
+            Error::Surface(e) => write!(f, "cannot draw to the window: {}", e),
+            Error::Socket(e) => write!(f, "{}", e),
+            Error::Output(e) => write!(f, "cannot print the configuration: {}", e),
+            Error::Cursor(e) => write!(f, "cannot read the cursor position: {}", e),
+            Error::Render(e) => write!(f, "cannot write the images: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<winit::error::EventLoopError> for Error {
+    fn from(e: winit::error::EventLoopError) -> Self {
+        Error::Display(e)
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(e: winit::error::OsError) -> Self {
+        Error::Window(e.into())
+    }
+}
+
+#[cfg(feature = "softbuffer")]
+impl From<softbuffer::SoftBufferError> for Error {
+    fn from(e: softbuffer::SoftBufferError) -> Self {
+        Error::Surface(e.into())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Socket(e)
+    }
+}
+
+/// Sends `message` to the journal as an error of `cursor-beacon`.
+fn journal(message: &str) -> std::io::Result<()> {
+    let entry = format!(
+        "PRIORITY=3\nSYSLOG_IDENTIFIER=cursor-beacon\nMESSAGE={}\n",
+        message.replace('\n', " ")
+    );
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to(entry.as_bytes(), JOURNAL_SOCKET)?;
+    Ok(())
+}
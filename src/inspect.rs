@@ -0,0 +1,236 @@
+//! `--print-config` and `--check`: report the effective settings without showing a beacon.
+
+use crate::error::Error;
+use crate::monitor::{self, MonitorInfo};
+use crate::xserver::XServer;
+use crate::{DEFAULT_REFRESH, Mode, Settings};
+use clap::ValueEnum;
+use log::{debug, warn};
+use std::time::Duration;
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::window::WindowId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Toml,
+    Json,
+}
+
+/// A connected monitor.
+pub struct Monitor {
+    name: Option<String>,
+    info: MonitorInfo,
+    refresh: Option<Duration>,
+}
+
+/// Collects the connected monitors, then exits.
+struct MonitorQuery {
+    with_size_mm: bool,
+    monitors: Vec<Monitor>,
+}
+
+impl ApplicationHandler for MonitorQuery {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.monitors = event_loop
+            .available_monitors()
+            .map(|monitor| Monitor {
+                name: monitor.name(),
+                info: MonitorInfo::new(&monitor, self.with_size_mm),
+                refresh: monitor::refresh_period(&monitor),
+            })
+            .collect();
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+/// The connected monitors, or none if the display cannot be opened.
+pub fn query_monitors(settings: &Settings) -> Vec<Monitor> {
+    let mut query = MonitorQuery {
+        with_size_mm: settings.needs_size_mm(),
+        monitors: Vec::new(),
+    };
+    let result = EventLoop::new().and_then(|event_loop| event_loop.run_app(&mut query));
+    if let Err(e) = result {
+        warn!("Cannot query the monitors: {}", e);
+    }
+    debug!("Monitors: count={}", query.monitors.len());
+    query.monitors
+}
+
+/// Prints `options` followed by the sizes they resolve to on each monitor,
+/// or on the fallback monitor if none is connected.
+pub fn print_config(
+    mut options: toml::Table,
+    settings: &Settings,
+    monitors: &[Monitor],
+    format: Format,
+) -> Result<(), Error> {
+    let mut monitors = monitors
+        .iter()
+        .map(|monitor| {
+            let info = &monitor.info;
+            let mut table = toml::Table::new();
+            if let Some(name) = &monitor.name {
+                table.insert("name".into(), name.clone().into());
+            }
+            table.insert("size".into(), pair(info.size).into());
+            table.insert("scale-factor".into(), info.scale_factor.into());
+            if let Some(size_mm) = info.size_mm {
+                table.insert("size-mm".into(), pair(size_mm).into());
+            }
+            table.insert("radius".into(), settings.radius(Some(info)).into());
+            table.insert("line-width".into(), settings.line_width(Some(info)).into());
+            let refresh = monitor.refresh.unwrap_or(DEFAULT_REFRESH);
+            table.insert("frame-interval".into(), frame_interval(settings, refresh));
+            toml::Value::Table(table)
+        })
+        .collect::<Vec<_>>();
+    // Without a monitor, report the defaults used when none is found, as `check` does.
+    if monitors.is_empty() {
+        let mut table = toml::Table::new();
+        table.insert("fallback".into(), true.into());
+        table.insert("radius".into(), settings.radius(None).into());
+        table.insert("line-width".into(), settings.line_width(None).into());
+        table.insert(
+            "frame-interval".into(),
+            frame_interval(settings, DEFAULT_REFRESH),
+        );
+        monitors.push(toml::Value::Table(table));
+    }
+    options.insert("monitor".into(), monitors.into());
+    options.insert("resolved".into(), resolved(settings).into());
+
+    match format {
+        Format::Toml => {
+            let text = toml::to_string(&options).map_err(|e| Error::Output(e.into()))?;
+            print!("{}", text);
+        }
+        Format::Json => {
+            let text =
+                serde_json::to_string_pretty(&options).map_err(|e| Error::Output(e.into()))?;
+            println!("{}", text);
+        }
+    }
+    Ok(())
+}
+
+/// Values that options like `auto` or a missing `--duration` resolve to.
+fn resolved(settings: &Settings) -> toml::Table {
+    let mut table = toml::Table::new();
+    let animation = settings.animation();
+    table.insert("duration".into(), millis(animation.duration()));
+    if let Some(fade) = animation.fade() {
+        table.insert("fade-start".into(), millis(fade.start));
+    }
+
+    let server = XServer::connect()
+        .inspect_err(|e| warn!("Cannot connect to the X server: {}", e))
+        .ok();
+    let transparency = settings.transparency().resolve(server.as_deref());
+    if let Some(value) = transparency.to_possible_value() {
+        table.insert("transparency".into(), value.get_name().into());
+    }
+    table
+}
+
+/// Time between frames on a monitor refreshing every `refresh` \[ms\]
+fn frame_interval(settings: &Settings, refresh: Duration) -> toml::Value {
+    millis(settings.animation().frame_interval(refresh))
+}
+
+/// `duration` in milliseconds, as the duration options are given
+fn millis(duration: Duration) -> toml::Value {
+    let ms = duration.as_secs_f64() * 1000.0;
+    if ms.fract() == 0.0 {
+        (ms as i64).into()
+    } else {
+        ((ms * 1000.0).round() / 1000.0).into()
+    }
+}
+
+fn pair((a, b): (u32, u32)) -> Vec<u32> {
+    vec![a, b]
+}
+
+/// Prints a diagnostic for every questionable combination.
+/// Returns false if any of them is an error.
+pub fn check(settings: &Settings, monitors: &[Monitor]) -> bool {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    // Without a monitor, sizes fall back to the defaults used when none is found.
+    let infos: Vec<Option<&MonitorInfo>> = if monitors.is_empty() {
+        vec![None]
+    } else {
+        monitors.iter().map(|monitor| Some(&monitor.info)).collect()
+    };
+    for (i, info) in infos.into_iter().enumerate() {
+        let on = match monitors.get(i) {
+            Some(Monitor {
+                name: Some(name), ..
+            }) => format!(" on {}", name),
+            Some(_) => format!(" on monitor {}", i),
+            None => String::new(),
+        };
+        let radius = settings.radius(info);
+        let line_width = settings.line_width(info);
+        if radius == 0 {
+            errors.push(format!("radius is 0 px{}", on));
+        } else if line_width >= radius {
+            errors.push(format!(
+                "line width ({} px) is not smaller than the radius ({} px){}",
+                line_width, radius, on
+            ));
+        }
+        if line_width == 0 {
+            warnings.push(format!("line width is 0 px{}, only the edge is drawn", on));
+        }
+    }
+
+    let animation = settings.animation();
+    if animation.duration() == Duration::ZERO {
+        errors.push("duration is 0, nothing would be shown".to_string());
+    }
+    if let Some(fade) = animation.fade()
+        && fade.start >= animation.duration()
+    {
+        warnings.push(format!(
+            "fade starts at {:?}, after the animation has ended ({:?})",
+            fade.start,
+            animation.duration()
+        ));
+    }
+
+    let appearance = settings.appearance();
+    match &appearance.mode {
+        Mode::Beacon | Mode::Crosshair => {
+            if alpha(appearance.color_argb) == 0 && alpha(appearance.edge_color_argb) == 0 {
+                errors.push("color and edge color are both fully transparent".to_string());
+            }
+        }
+        Mode::Spotlight(spotlight) => {
+            if alpha(spotlight.dim_argb) == 0 {
+                errors.push("dim color is fully transparent, nothing would be dimmed".to_string());
+            }
+        }
+    }
+
+    for warning in &warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &errors {
+        eprintln!("error: {}", error);
+    }
+    if errors.is_empty() {
+        eprintln!("Configuration OK");
+    }
+    errors.is_empty()
+}
+
+fn alpha(argb: u32) -> u32 {
+    argb >> 24
+}
@@ -1,44 +1,257 @@
-use clap::Parser;
+use animation::{Animation, Easing, Fade, Style};
+use beacon::{Appearance, Beacon};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use click::{Button, Click};
+use config::ConfigError;
 use csscolorparser::Color;
-use device_query::{DeviceQuery, DeviceState, MouseState};
-use log::{debug, info};
-use std::num::NonZeroU32;
+use cursor::{CursorConfig, CursorSource, CursorSourceKind};
+use daemon::Request;
+use error::Error;
+use log::{debug, info, warn};
+use monitor::MonitorInfo;
+use render::{RenderConfig, RenderFormat};
+use shake::ShakeConfig;
+use shape::{Shape, ShapeKind};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
+use transparency::Transparency;
 use winit::application::ApplicationHandler;
-use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::platform::x11::WindowAttributesExtX11;
-use winit::window::{Window, WindowId};
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+use winit::window::WindowId;
+use xserver::XServer;
+
+mod animation;
+mod argb;
+mod beacon;
+mod click;
+mod config;
+mod cursor;
+mod daemon;
+mod error;
+mod inspect;
+mod monitor;
+#[cfg(all(feature = "softbuffer", not(feature = "native-x11")))]
+mod overlay;
+mod raster;
+mod render;
+mod shake;
+mod shape;
+mod transparency;
+#[cfg(feature = "native-x11")]
+mod x11_overlay;
+mod xserver;
+#[cfg(feature = "native-x11")]
+use x11_overlay as overlay;
+
+#[cfg(not(any(feature = "softbuffer", feature = "native-x11")))]
+compile_error!("a window backend is required: enable the `softbuffer` or `native-x11` feature");
+
+fn main() -> ExitCode {
     env_logger::init();
 
-    let args = Args::parse();
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            e.report();
+            e.exit_code()
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let (args, matches) = Args::load()?;
     debug!("Argument: {:?}", args);
 
+    if args.print_config.is_some() || args.check {
+        let settings = args.create_settings();
+        let monitors = inspect::query_monitors(&settings);
+
+        if let Some(format) = args.print_config {
+            let table = config::to_table(&matches, &Args::command());
+            inspect::print_config(table, &settings, &monitors, format)?;
+        }
+        if args.check && !inspect::check(&settings, &monitors) {
+            return Err(Error::Check);
+        }
+        return Ok(());
+    }
+
+    let repeat_request = args.on_repeat.request();
+    let persistent = match &args.command {
+        None => false,
+        Some(Command::Daemon | Command::Shake(_) | Command::Click(_)) => true,
+        Some(Command::Trigger) => return Ok(daemon::send(repeat_request)?),
+        Some(Command::Render(render_args)) => {
+            return render::render(&args.create_settings(), &render_args.create_config());
+        }
+    };
+
+    // A plain invocation takes the socket first, as the lock against stacking windows,
+    // and otherwise hands over to the running instance.
+    let bound = match args.command {
+        None => match daemon::bind_or_send(repeat_request) {
+            Ok(Some(bound)) => Some(bound),
+            Ok(None) => {
+                info!("Handed over to the running instance");
+                return Ok(());
+            }
+            Err(e) => {
+                warn!("Cannot listen for repeated invocations: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
     let settings = args.create_settings();
-    let mut app = App::new(settings);
+    let cursor_config = args.create_cursor_config().map_err(Error::Cursor)?;
+    // The display is opened first, so a missing one is reported as such.
+    let event_loop = EventLoop::<Request>::with_user_event().build()?;
+    let cursor = cursor_config.open().map_err(Error::Cursor)?;
+    let mut app = App::new(settings, persistent, cursor);
+
+    match &args.command {
+        None => {
+            if let Some((listener, socket)) = bound {
+                let proxy = event_loop.create_proxy();
+                std::thread::spawn(move || daemon::serve(listener, proxy));
+                app.socket = Some(socket);
+            }
+        }
+        Some(Command::Daemon) => {
+            let (listener, socket) = daemon::bind()?;
+            let proxy = event_loop.create_proxy();
+            std::thread::spawn(move || daemon::serve(listener, proxy));
+            app.socket = Some(socket);
+        }
+        Some(Command::Shake(shake_args)) => {
+            let config = shake_args.create_config();
+            let proxy = event_loop.create_proxy();
+            std::thread::spawn(move || shake::watch(config, cursor_config, proxy));
+        }
+        Some(Command::Click(click_args)) => {
+            let sample_interval = click_args.sample_interval;
+            let proxy = event_loop.create_proxy();
+            std::thread::spawn(move || click::watch(sample_interval, proxy));
+        }
+        Some(Command::Trigger | Command::Render(_)) => (),
+    }
 
-    let event_loop = EventLoop::new()?;
     event_loop.set_control_flow(ControlFlow::Wait);
-    event_loop.run_app(&mut app).map_err(Into::into)
+    let result = event_loop.run_app(&mut app);
+
+    match app.error.take() {
+        Some(e) => Err(e),
+        None => Ok(result?),
+    }
+}
+
+/// Error parsing a `Length`, `Radius` or `LineWidth`.
+#[derive(Debug, Clone, PartialEq)]
+enum ParseLengthError {
+    Number(String),
+    Negative(String),
+    Unit {
+        unit: String,
+        expected: &'static str,
+    },
+}
+
+impl std::fmt::Display for ParseLengthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseLengthError::Number(s) => write!(f, "invalid number {:?}", s),
+            ParseLengthError::Negative(s) => write!(f, "negative size {:?}", s),
+            ParseLengthError::Unit { unit, expected } => {
+                write!(f, "unrecognized unit {:?} (expected {})", unit, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseLengthError {}
+
+/// A size in physical pixels, logical pixels, millimetres or relative to the monitor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Length {
+    Pixels(u32),
+    /// Multiplied by the monitor's scale factor
+    Logical(f32),
+    /// Converted with the monitor's physical size
+    Millimeters(f32),
+    /// Percentage of the monitor's longer side
+    Percent(f32),
+}
+
+impl Length {
+    const UNITS: &'static str = "px, lp, mm or %";
+
+    /// Splits `s` into a non-negative number and the unit following it.
+    fn split_unit(s: &str) -> Result<(f32, &str), ParseLengthError> {
+        let s = s.trim();
+        let end = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(end);
+
+        let value = number
+            .trim()
+            .parse::<f32>()
+            .map_err(|_| ParseLengthError::Number(s.to_string()))?;
+        if value < 0.0 {
+            return Err(ParseLengthError::Negative(s.to_string()));
+        }
+        Ok((value, unit.trim()))
+    }
+
+    fn to_pixels(self, monitor: Option<&MonitorInfo>) -> u32 {
+        let pixels = match self {
+            Length::Pixels(v) => return v,
+            Length::Logical(v) => v as f64 * monitor.map_or(1.0, |m| m.scale_factor),
+            Length::Millimeters(v) => {
+                let pixels_per_mm = monitor.and_then(MonitorInfo::pixels_per_mm);
+                v as f64 * pixels_per_mm.unwrap_or(monitor::DEFAULT_PIXELS_PER_MM)
+            }
+            Length::Percent(v) => v as f64 / 100.0 * monitor::longer_side(monitor) as f64,
+        };
+        pixels.round().max(0.0) as u32
+    }
+}
+
+impl std::str::FromStr for Length {
+    type Err = ParseLengthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = Self::split_unit(s)?;
+        match unit {
+            "" | "px" => Ok(Length::Pixels(value.round() as u32)),
+            "lp" => Ok(Length::Logical(value)),
+            "mm" => Ok(Length::Millimeters(value)),
+            "%" => Ok(Length::Percent(value)),
+            _ => Err(ParseLengthError::Unit {
+                unit: unit.to_string(),
+                expected: Self::UNITS,
+            }),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 enum Radius {
     Auto,
-    Value(u32),
+    Value(Length),
 }
 
 impl std::str::FromStr for Radius {
-    type Err = <u32 as std::str::FromStr>::Err;
+    type Err = <Length as std::str::FromStr>::Err;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "auto" => Ok(Radius::Auto),
-            _ => s.parse::<u32>().map(Radius::Value),
+            s => s.parse::<Length>().map(Radius::Value),
         }
     }
 }
@@ -46,28 +259,239 @@ impl std::str::FromStr for Radius {
 #[derive(Debug, Clone)]
 enum LineWidth {
     Auto,
-    Value(u32),
+    Value(Length),
+    /// Percentage of the radius
+    RadiusPercent(f32),
 }
 
 impl std::str::FromStr for LineWidth {
-    type Err = <u32 as std::str::FromStr>::Err;
+    type Err = <Length as std::str::FromStr>::Err;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "auto" => Ok(LineWidth::Auto),
-            _ => s.parse::<u32>().map(LineWidth::Value),
+        let s = s.to_lowercase();
+        if s == "auto" {
+            return Ok(LineWidth::Auto);
+        }
+
+        match Length::split_unit(&s)? {
+            (value, "%r") => Ok(LineWidth::RadiusPercent(value)),
+            (_, unit) => s
+                .parse::<Length>()
+                .map(LineWidth::Value)
+                .map_err(|e| match e {
+                    ParseLengthError::Unit { .. } => ParseLengthError::Unit {
+                        unit: unit.to_string(),
+                        expected: "px, lp, mm, % or %r",
+                    },
+                    e => e,
+                }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ModeKind {
+    /// Shape around the cursor
+    Beacon,
+    /// Dim the whole monitor except around the cursor
+    Spotlight,
+    /// Lines through the cursor spanning the whole monitor
+    Crosshair,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Repeat {
+    /// Restart the animation at the new cursor position
+    Restart,
+    /// Play the running animation once more
+    Extend,
+}
+
+impl Repeat {
+    fn request(self) -> Request {
+        match self {
+            Repeat::Restart => Request::Show,
+            Repeat::Extend => Request::Extend,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Keep running in the background and show the beacon on every `trigger`
+    Daemon,
+    /// Ask a running daemon to show the beacon
+    Trigger,
+    /// Keep running in the background and show the beacon when the mouse is shaken
+    Shake(ShakeArgs),
+    /// Keep running in the background and show a ring at every mouse click
+    Click(ClickArgs),
+    /// Write the animation to image files instead of showing it
+    Render(RenderArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ShakeArgs {
+    /// Pointer sampling interval \[ms\]
+    #[arg(long, default_value = "10", value_parser = Args::parse_millis)]
+    sample_interval: Duration,
+
+    /// Time window in which the direction reversals have to happen \[ms\]
+    #[arg(long, default_value = "600", value_parser = Args::parse_millis)]
+    window: Duration,
+
+    /// Direction reversals required within the window
+    #[arg(long, default_value = "4")]
+    reversals: usize,
+
+    /// Minimum travel between two reversals \[px\]
+    #[arg(long, default_value = "40")]
+    min_distance: u32,
+
+    /// Minimum time between two beacons \[ms\]
+    #[arg(long, default_value = "1000", value_parser = Args::parse_millis)]
+    cooldown: Duration,
+}
+
+#[derive(clap::Args, Debug)]
+struct ClickArgs {
+    /// Mouse button sampling interval \[ms\]
+    #[arg(long, default_value = "10", value_parser = Args::parse_millis)]
+    sample_interval: Duration,
+
+    /// Ring color for the left button (CSS color format)
+    #[arg(long, default_value = "orangered", value_parser = csscolorparser::parse)]
+    left_color: Color,
+
+    /// Ring color for the middle button (CSS color format)
+    #[arg(long, default_value = "limegreen", value_parser = csscolorparser::parse)]
+    middle_color: Color,
+
+    /// Ring color for the right button (CSS color format)
+    #[arg(long, default_value = "dodgerblue", value_parser = csscolorparser::parse)]
+    right_color: Color,
+
+    /// Animation style on button press
+    #[arg(long, value_enum, default_value_t = Style::Expand)]
+    press_style: Style,
+
+    /// Animation style on button release
+    #[arg(long, value_enum, default_value_t = Style::Shrink)]
+    release_style: Style,
+}
+
+#[derive(clap::Args, Debug)]
+struct RenderArgs {
+    /// Output file, or directory for `png`
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = RenderFormat::Png)]
+    format: RenderFormat,
+
+    /// Size of the monitor the sizes are resolved for \[px\]
+    #[arg(long, default_value = "1920x1080", value_parser = RenderArgs::parse_size)]
+    monitor_size: (u32, u32),
+
+    /// Scale factor of the monitor
+    #[arg(long, default_value = "1")]
+    scale_factor: f64,
+
+    /// Refresh rate of the monitor \[Hz\]
+    #[arg(long, default_value = "60", value_parser = RenderArgs::parse_refresh_rate)]
+    refresh_rate: f64,
+
+    /// Color behind the beacon (CSS color format)
+    #[arg(long, default_value = "transparent", value_parser = csscolorparser::parse)]
+    background: Color,
+}
+
+impl RenderArgs {
+    fn parse_size(arg: &str) -> Result<(u32, u32), String> {
+        let parse = |s: &str| s.parse::<u32>().ok().filter(|&n| n > 0);
+        match arg.split_once('x') {
+            Some((width, height)) => parse(width).zip(parse(height)),
+            None => None,
+        }
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {:?}", arg))
+    }
+
+    fn parse_refresh_rate(arg: &str) -> Result<f64, String> {
+        match arg.parse::<f64>() {
+            Ok(hz) if hz.is_finite() && hz > 0.0 => Ok(hz),
+            _ => Err(format!("expected a positive number, got {:?}", arg)),
+        }
+    }
+
+    fn create_config(&self) -> RenderConfig {
+        RenderConfig {
+            output: self.output.clone(),
+            format: self.format,
+            monitor: MonitorInfo {
+                size: self.monitor_size,
+                scale_factor: self.scale_factor,
+                size_mm: None,
+            },
+            refresh: Duration::from_secs_f64(1.0 / self.refresh_rate),
+            background_argb: argb::from_color(&self.background),
+        }
+    }
+}
+
+impl ClickArgs {
+    fn create_settings(&self) -> ClickSettings {
+        ClickSettings {
+            left_argb: argb::from_color(&self.left_color),
+            middle_argb: argb::from_color(&self.middle_color),
+            right_argb: argb::from_color(&self.right_color),
+            press_style: self.press_style,
+            release_style: self.release_style,
+        }
+    }
+}
+
+impl ShakeArgs {
+    fn create_config(&self) -> ShakeConfig {
+        ShakeConfig {
+            sample_interval: self.sample_interval,
+            window: self.window,
+            reversals: self.reversals,
+            min_distance: self.min_distance,
+            cooldown: self.cooldown,
         }
     }
 }
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_override_self = true)]
 struct Args {
-    /// Circle radius \[px\]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Configuration file \[default: $XDG_CONFIG_HOME/cursor-beacon/config.toml\]
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Profile of the configuration file to apply
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// Print the effective configuration and exit
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "toml")]
+    print_config: Option<inspect::Format>,
+
+    /// Validate the effective configuration and exit
+    #[arg(long)]
+    check: bool,
+
+    /// Circle radius: "auto", pixels, "<n>lp" (logical pixels), "<n>mm"
+    /// or "<n>%" (of the monitor's longer side)
     #[arg(short, long, default_value = "auto")]
     radius: Radius,
 
-    /// Line width \[px\]
+    /// Line width: "auto", pixels, "<n>lp" (logical pixels), "<n>mm",
+    /// "<n>%" (of the monitor's longer side) or "<n>%r" (of the radius)
     #[arg(short, long, default_value = "auto")]
     line_width: LineWidth,
 
@@ -79,274 +503,506 @@ struct Args {
     #[arg(short, long, default_value = "gray", value_parser = csscolorparser::parse)]
     edge_color: Color,
 
-    /// Frame interval \[ms\]
+    /// Frame interval of the steps easing \[ms\]
     #[arg(short, long, default_value = "70", value_parser = Args::parse_millis)]
     interval: Duration,
+
+    /// Animation duration \[ms\] (default: 4 frame intervals)
+    #[arg(short, long, value_parser = Args::parse_millis)]
+    duration: Option<Duration>,
+
+    /// Animation easing curve
+    #[arg(long, value_enum, default_value_t = Easing::Steps)]
+    easing: Easing,
+
+    /// Animation style
+    #[arg(long, value_enum, default_value_t = Style::Shrink)]
+    style: Style,
+
+    /// Number of pulses, ripples or blinks
+    #[arg(long, default_value = "3")]
+    count: u32,
+
+    /// Time from the start of the animation until it begins to fade out \[ms\]
+    #[arg(long, value_parser = Args::parse_millis)]
+    fade_start: Option<Duration>,
+
+    /// Fade-out easing curve
+    #[arg(long, value_enum, default_value_t = Easing::Linear)]
+    fade_easing: Easing,
+
+    /// Beacon shape
+    #[arg(short, long, value_enum, default_value_t = ShapeKind::Ring)]
+    shape: ShapeKind,
+
+    /// Arrow length relative to the radius (arrows shape)
+    #[arg(long, default_value = "0.5")]
+    arrow_length: f32,
+
+    /// Gap around the cursor relative to the radius (crosshair shape)
+    #[arg(long, default_value = "0.2")]
+    crosshair_gap: f32,
+
+    /// Display mode
+    #[arg(short, long, value_enum, default_value_t = ModeKind::Beacon)]
+    mode: ModeKind,
+
+    /// Dimming color (CSS color format, spotlight mode)
+    #[arg(long, default_value = "rgba(0, 0, 0, 0.6)", value_parser = csscolorparser::parse)]
+    dim_color: Color,
+
+    /// Softness of the spotlight edge \[px\]
+    #[arg(long, default_value = "0")]
+    softness: u32,
+
+    /// Keep the beacon where the cursor was at startup instead of following it
+    #[arg(long)]
+    fixed_position: bool,
+
+    /// Where the cursor position is read from
+    #[arg(long, value_enum, default_value_t = CursorSourceKind::DeviceQuery)]
+    cursor_source: CursorSourceKind,
+
+    /// File of "x y" lines replayed by the script cursor source
+    #[arg(long, value_name = "FILE")]
+    cursor_script: Option<PathBuf>,
+
+    /// How the window is made see-through (shape works without a compositing manager)
+    #[arg(long, value_enum, default_value_t = Transparency::Auto)]
+    transparency: Transparency,
+
+    /// What a repeated invocation (or `trigger`) does while the beacon is shown
+    #[arg(long, value_enum, default_value_t = Repeat::Restart)]
+    on_repeat: Repeat,
 }
 
 impl Args {
+    /// Parses the command line on top of the configuration file.
+    fn load() -> Result<(Self, clap::ArgMatches), ConfigError> {
+        let matches = Self::command().get_matches();
+        let args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+        let path = match &args.config {
+            Some(path) => path.clone(),
+            None => match config::default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None if args.profile.is_some() => return Err(ConfigError::NoFile),
+                None => return Ok((args, matches)),
+            },
+        };
+        info!("Configuration: {}", path.display());
+
+        let config_args = config::load(&path, args.profile.as_deref(), &Self::command())?;
+        debug!("Configuration arguments: {:?}", config_args);
+
+        // Later occurrences override earlier ones, so the real arguments go last.
+        let mut argv = std::env::args_os();
+        let program = argv.next();
+        let matches = Self::command().get_matches_from(
+            program
+                .into_iter()
+                .chain(config_args.into_iter().map(Into::into))
+                .chain(argv),
+        );
+        let args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        Ok((args, matches))
+    }
+
     fn parse_millis(arg: &str) -> Result<Duration, <u64 as std::str::FromStr>::Err> {
         arg.parse::<u64>().map(Duration::from_millis)
     }
 
-    fn color_to_argb(color: &Color) -> u32 {
-        let [r, g, b, a] = color.to_rgba8();
-        (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32
+    fn create_shape(&self) -> Box<dyn Shape> {
+        match self.shape {
+            ShapeKind::Ring => Box::new(shape::Ring),
+            ShapeKind::Disc => Box::new(shape::Disc),
+            ShapeKind::Square => Box::new(shape::Square),
+            ShapeKind::Diamond => Box::new(shape::Diamond),
+            ShapeKind::Arrows => Box::new(shape::Arrows::new(self.arrow_length)),
+            ShapeKind::Crosshair => Box::new(shape::Crosshair::new(self.crosshair_gap)),
+        }
+    }
+
+    fn create_animation(&self) -> Animation {
+        let duration = self.duration.unwrap_or(self.interval * animation::STEPS);
+        let fade = self.fade_start.map(|start| Fade {
+            start,
+            easing: self.fade_easing,
+        });
+        Animation::new(duration, self.easing, self.style, self.count, fade)
+    }
+
+    fn create_mode(&self) -> Mode {
+        match self.mode {
+            ModeKind::Beacon => Mode::Beacon,
+            ModeKind::Spotlight => Mode::Spotlight(Spotlight {
+                dim_argb: argb::from_color(&self.dim_color),
+                softness: self.softness,
+            }),
+            ModeKind::Crosshair => Mode::Crosshair,
+        }
+    }
+
+    fn create_cursor_config(&self) -> Result<CursorConfig, Box<dyn std::error::Error>> {
+        CursorConfig::new(self.cursor_source, self.cursor_script.as_deref())
     }
 
     fn create_settings(&self) -> Settings {
-        let color_argb = Self::color_to_argb(&self.color);
-        let edge_color_argb = Self::color_to_argb(&self.edge_color);
+        let color_argb = argb::from_color(&self.color);
+        let edge_color_argb = argb::from_color(&self.edge_color);
 
-        Settings::new(
-            self.radius.clone(),
-            self.line_width.clone(),
+        Settings {
+            radius: self.radius.clone(),
+            line_width: self.line_width.clone(),
             color_argb,
             edge_color_argb,
-            self.interval,
-        )
+            animation: self.create_animation(),
+            shape: self.create_shape(),
+            mode: self.create_mode(),
+            follow_cursor: !self.fixed_position,
+            transparency: self.transparency,
+            click: match &self.command {
+                Some(Command::Click(click_args)) => Some(click_args.create_settings()),
+                _ => None,
+            },
+        }
     }
 }
 
+/// Frame period used when the monitor does not report its refresh rate
+const DEFAULT_REFRESH: Duration = Duration::from_micros(16_667);
+
 struct App {
     settings: Settings,
-    window: Option<Rc<Window>>,
-    draw_buffer: Option<DrawBuffer>,
-
-    radius_value: u32,
-    line_width_value: u32,
-
-    update_count: u32,
-    next_update: Instant,
+    /// Keep the event loop alive after the animation (daemon mode)
+    persistent: bool,
+    cursor: Box<dyn CursorSource>,
+    beacons: Vec<Beacon>,
+    /// First error that stopped the event loop
+    error: Option<Error>,
+    /// Control socket, removed before exiting so no request reaches an exiting instance
+    socket: Option<daemon::SocketFile>,
+    /// Shared by all beacon windows, if the X server can be reached
+    server: Option<Rc<XServer>>,
 }
 
 impl App {
-    fn new(settings: Settings) -> Self {
+    fn new(mut settings: Settings, persistent: bool, cursor: Box<dyn CursorSource>) -> Self {
+        let server = XServer::connect()
+            .inspect_err(|e| warn!("Cannot connect to the X server: {}", e))
+            .ok();
+        settings.transparency = settings.transparency.resolve(server.as_deref());
+
         Self {
             settings,
-            window: None,
-            draw_buffer: None,
-            radius_value: 0,
-            line_width_value: 0,
-            update_count: 0,
-            next_update: Instant::now(),
+            persistent,
+            cursor,
+            beacons: Vec::new(),
+            error: None,
+            socket: None,
+            server,
         }
     }
-}
 
-impl ApplicationHandler for App {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let monitor_size = if let Some(monitor) = event_loop.primary_monitor() {
-            let s = monitor.size();
-            Some((s.width, s.height))
-        } else {
-            None
+    /// Shows the beacon at the cursor, restarting the animation if one is running.
+    fn show(&mut self, event_loop: &ActiveEventLoop) -> Result<(), Error> {
+        let Some(cursor_position) = self.cursor.position() else {
+            warn!("Cannot show the beacon without the cursor position");
+            return Ok(());
         };
+        info!("Cursor Position: {:?}", cursor_position);
 
-        let win_size = (self.settings.radius(monitor_size) * 2) as i32;
+        self.beacons.clear();
+        let beacon = Beacon::new(
+            event_loop,
+            &self.settings,
+            self.server.as_ref(),
+            cursor_position,
+            self.settings.appearance(),
+        )?;
+        self.beacons.push(beacon);
+        Ok(())
+    }
 
-        let device_state = DeviceState::new();
-        let mouse: MouseState = device_state.get_mouse();
-        let cursor_position = mouse.coords;
-        info!("Cursor Position: {:?}", cursor_position);
+    /// Adds a click ring on top of the running ones.
+    fn show_click(&mut self, event_loop: &ActiveEventLoop, click: Click) -> Result<(), Error> {
+        let Some(click_settings) = self.settings.click() else {
+            return Ok(());
+        };
 
-        let attr = Window::default_attributes()
-            .with_transparent(true)
-            .with_decorations(false)
-            .with_inner_size(PhysicalSize::new(win_size, win_size))
-            .with_position(PhysicalPosition::new(
-                cursor_position.0 - win_size / 2,
-                cursor_position.1 - win_size / 2,
-            ))
-            // X11
-            .with_override_redirect(true);
+        let color_argb = match click.button {
+            Button::Left => click_settings.left_argb,
+            Button::Middle => click_settings.middle_argb,
+            Button::Right => click_settings.right_argb,
+        };
+        let style = if click.pressed {
+            click_settings.press_style
+        } else {
+            click_settings.release_style
+        };
 
-        let window = Rc::new(event_loop.create_window(attr).unwrap());
+        let appearance = Appearance {
+            mode: Mode::Beacon,
+            animation: self.settings.animation().with_style(style),
+            color_argb,
+            edge_color_argb: self.settings.edge_color_argb(),
+            follow_cursor: false,
+        };
+        let beacon = Beacon::new(
+            event_loop,
+            &self.settings,
+            self.server.as_ref(),
+            click.position,
+            appearance,
+        )?;
+        self.beacons.push(beacon);
+        Ok(())
+    }
 
-        self.draw_buffer = Some(DrawBuffer::new(window.clone()));
-        self.window = Some(window);
-        self.radius_value = self.settings.radius(monitor_size);
-        self.line_width_value = self.settings.line_width(monitor_size);
+    /// Keeps `error` for `main` and stops the event loop.
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: Error) {
+        self.error.get_or_insert(error);
+        self.socket = None;
+        event_loop.exit();
     }
 
-    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        let now = Instant::now();
+    /// Exits once no beacon is left, unless running as a daemon.
+    fn exit_if_idle(&mut self, event_loop: &ActiveEventLoop) {
+        if self.beacons.is_empty() && !self.persistent {
+            self.socket = None;
+            event_loop.exit();
+        }
+    }
+}
 
-        if now >= self.next_update {
-            self.update_count += 1;
+impl ApplicationHandler<Request> for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if !self.persistent
+            && let Err(e) = self.show(event_loop)
+        {
+            self.fail(event_loop, e);
+        }
+    }
 
-            if self.update_count > 4 {
-                event_loop.exit();
-                return;
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, request: Request) {
+        let result = match request {
+            Request::Show => self.show(event_loop),
+            Request::Extend if !self.beacons.is_empty() => {
+                self.beacons.iter_mut().for_each(Beacon::extend);
+                Ok(())
             }
+            Request::Extend => self.show(event_loop),
+            Request::Click(click) => self.show_click(event_loop, click),
+        };
+        if let Err(e) = result {
+            self.fail(event_loop, e);
+        }
+    }
 
-            if let Some(window) = &self.window {
-                window.request_redraw();
-            }
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let now = Instant::now();
 
-            self.next_update = now + *self.settings.interval();
+        let cursor_position = self
+            .beacons
+            .iter()
+            .any(|beacon| beacon.follows_cursor() && now >= beacon.next_update())
+            .then(|| self.cursor.position())
+            .flatten();
+
+        self.beacons.retain_mut(|beacon| {
+            let cursor_position = cursor_position.filter(|_| beacon.follows_cursor());
+            now < beacon.next_update() || beacon.update(now, cursor_position)
+        });
+
+        // Without redraw events, frames are drawn right after the update.
+        let shape = self.settings.shape();
+        let result = self
+            .beacons
+            .iter_mut()
+            .filter(|beacon| beacon.take_pending_redraw())
+            .try_for_each(|beacon| beacon.draw(shape));
+        if let Err(e) = result {
+            self.fail(event_loop, e);
         }
+        self.exit_if_idle(event_loop);
 
-        event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_update));
+        match self.beacons.iter().map(Beacon::next_update).min() {
+            Some(next_update) => event_loop.set_control_flow(ControlFlow::WaitUntil(next_update)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
         match event {
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                self.beacons.retain(|beacon| beacon.window_id() != id);
+                self.exit_if_idle(event_loop);
+            }
             WindowEvent::RedrawRequested => {
-                debug!("Frame {}", self.update_count);
-
-                let current_radius = self.radius_value / (self.update_count + 1);
-
-                self.draw_buffer.as_mut().unwrap().draw_circle(
-                    current_radius,
-                    self.line_width_value,
-                    self.settings.color_argb(),
-                    self.settings.edge_color_argb(),
-                );
+                if let Some(beacon) = self.beacons.iter_mut().find(|b| b.window_id() == id)
+                    && let Err(e) = beacon.draw(self.settings.shape())
+                {
+                    self.fail(event_loop, e);
+                }
             }
             _ => (),
         }
     }
 }
 
+#[derive(Clone)]
+enum Mode {
+    Beacon,
+    Spotlight(Spotlight),
+    Crosshair,
+}
+
+#[derive(Clone)]
+struct Spotlight {
+    dim_argb: u32,
+    /// Width of the soft edge around the hole \[px\]
+    softness: u32,
+}
+
 struct Settings {
     radius: Radius,
     line_width: LineWidth,
     color_argb: u32,
     edge_color_argb: u32,
-    interval: Duration,
+    animation: Animation,
+    shape: Box<dyn Shape>,
+    mode: Mode,
+    follow_cursor: bool,
+    transparency: Transparency,
+    click: Option<ClickSettings>,
 }
 
-impl Settings {
-    fn new(
-        radius: Radius,
-        line_width: LineWidth,
-        color_argb: u32,
-        edge_color_argb: u32,
-        interval: Duration,
-    ) -> Self {
-        Self {
-            radius,
-            line_width,
-            color_argb,
-            edge_color_argb,
-            interval,
-        }
-    }
+struct ClickSettings {
+    left_argb: u32,
+    middle_argb: u32,
+    right_argb: u32,
+    press_style: Style,
+    release_style: Style,
+}
 
-    fn radius(&self, monitor_size: Option<(u32, u32)>) -> u32 {
+impl Settings {
+    fn radius(&self, monitor: Option<&MonitorInfo>) -> u32 {
         match self.radius {
-            Radius::Value(v) => v,
+            Radius::Value(v) => v.to_pixels(monitor),
             Radius::Auto => {
-                let s = if let Some((w, h)) = monitor_size {
-                    std::cmp::max(w, h)
-                } else {
-                    1920 // Full HD
-                };
-
+                let s = monitor::longer_side(monitor);
                 std::cmp::max(s / 15, 50)
             }
         }
     }
 
-    fn line_width(&self, monitor_size: Option<(u32, u32)>) -> u32 {
+    fn line_width(&self, monitor: Option<&MonitorInfo>) -> u32 {
         match self.line_width {
-            LineWidth::Value(v) => v,
+            LineWidth::Value(v) => v.to_pixels(monitor),
+            LineWidth::RadiusPercent(v) => (v / 100.0 * self.radius(monitor) as f32).round() as u32,
             LineWidth::Auto => {
-                let s = if let Some((w, h)) = monitor_size {
-                    std::cmp::max(w, h)
-                } else {
-                    1920 // Full HD
-                };
-
+                let s = monitor::longer_side(monitor);
                 std::cmp::max(s / 600, 3)
             }
         }
     }
 
-    fn color_argb(&self) -> u32 {
-        self.color_argb
+    /// Whether resolving the sizes needs the monitor's physical size.
+    fn needs_size_mm(&self) -> bool {
+        matches!(self.radius, Radius::Value(Length::Millimeters(_)))
+            || matches!(self.line_width, LineWidth::Value(Length::Millimeters(_)))
+    }
+
+    fn transparency(&self) -> Transparency {
+        self.transparency
     }
 
     fn edge_color_argb(&self) -> u32 {
         self.edge_color_argb
     }
 
-    fn interval(&self) -> &Duration {
-        &self.interval
+    fn animation(&self) -> &Animation {
+        &self.animation
     }
-}
 
-struct DrawBuffer {
-    surface: softbuffer::Surface<Rc<Window>, Rc<Window>>,
-    _context: softbuffer::Context<Rc<Window>>,
-}
+    fn shape(&self) -> &dyn Shape {
+        self.shape.as_ref()
+    }
 
-impl DrawBuffer {
-    fn new(window: Rc<Window>) -> Self {
-        let context = softbuffer::Context::new(window.clone()).unwrap();
-        let surface = softbuffer::Surface::new(&context, window).unwrap();
+    fn click(&self) -> Option<&ClickSettings> {
+        self.click.as_ref()
+    }
 
-        Self {
-            surface,
-            _context: context,
+    fn appearance(&self) -> Appearance {
+        Appearance {
+            mode: self.mode.clone(),
+            animation: self.animation.clone(),
+            color_argb: self.color_argb,
+            edge_color_argb: self.edge_color_argb,
+            follow_cursor: self.follow_cursor,
         }
     }
+}
 
-    fn window_size(&self) -> (u32, u32) {
-        let size = self.surface.window().inner_size();
-        (size.width, size.height)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_with_units() {
+        assert_eq!("5%".parse(), Ok(Length::Percent(5.0)));
+        assert_eq!("2mm".parse(), Ok(Length::Millimeters(2.0)));
+        assert_eq!("40lp".parse(), Ok(Length::Logical(40.0)));
+        assert_eq!(" 12 px ".parse(), Ok(Length::Pixels(12)));
+        assert!(matches!(
+            "40lp".parse(),
+            Ok(Radius::Value(Length::Logical(40.0)))
+        ));
+        assert!(matches!("10%r".parse(), Ok(LineWidth::RadiusPercent(10.0))));
     }
 
-    fn draw_circle(&mut self, radius: u32, line_width: u32, color_argb: u32, edge_color_argb: u32) {
-        debug!(
-            "Draw circle: radius={}px, line_width={}px, color={:#x}, edge_color={:#x}",
-            radius, line_width, color_argb, edge_color_argb
+    #[test]
+    fn radius_percent_is_only_for_the_line_width() {
+        assert_eq!(
+            "10%r".parse::<Radius>().unwrap_err(),
+            ParseLengthError::Unit {
+                unit: "%r".to_string(),
+                expected: Length::UNITS,
+            }
         );
+    }
 
-        let (w, h) = self.window_size();
-
-        self.surface
-            .resize(NonZeroU32::new(w).unwrap(), NonZeroU32::new(h).unwrap())
-            .unwrap();
-
-        let mut buffer = self.surface.buffer_mut().unwrap();
-
-        let center_x = w / 2;
-        let center_y = h / 2;
-
-        let radius_outer = radius;
-        let radius_line_outer = radius_outer.saturating_sub(1);
-        let radius_line_inner = radius_line_outer.saturating_sub(line_width);
-        let radius_inner = radius_line_inner.saturating_sub(1);
-
-        let radius_outer_sq = radius_outer.pow(2);
-        let radius_line_outer_sq = radius_line_outer.pow(2);
-        let radius_line_inner_sq = radius_line_inner.pow(2);
-        let radius_inner_sq = radius_inner.pow(2);
-
-        for y in 0..h {
-            let idx_y = y * w;
-            let dist_y = y.abs_diff(center_y).pow(2);
-            for x in 0..w {
-                let idx = (idx_y + x) as usize;
-                let dist_x = x.abs_diff(center_x).pow(2);
-                let dist_sq = dist_x + dist_y;
-
-                // 0xAA RR GG BB
-                buffer[idx] = if (radius_inner_sq <= dist_sq && dist_sq < radius_line_inner_sq)
-                    || (radius_line_outer_sq < dist_sq && dist_sq <= radius_outer_sq)
-                {
-                    edge_color_argb
-                } else if radius_line_inner_sq <= dist_sq && dist_sq <= radius_line_outer_sq {
-                    color_argb
-                } else {
-                    0x00000000
-                };
+    #[test]
+    fn unknown_unit_lists_the_expected_ones() {
+        assert_eq!(
+            "5q".parse::<Length>(),
+            Err(ParseLengthError::Unit {
+                unit: "q".to_string(),
+                expected: Length::UNITS,
+            })
+        );
+        assert_eq!(
+            "5q".parse::<LineWidth>().unwrap_err(),
+            ParseLengthError::Unit {
+                unit: "q".to_string(),
+                expected: "px, lp, mm, % or %r",
             }
-        }
+        );
+    }
 
-        buffer.present().unwrap();
+    #[test]
+    fn negative_and_missing_numbers() {
+        assert_eq!(
+            "-3mm".parse::<Length>(),
+            Err(ParseLengthError::Negative("-3mm".to_string()))
+        );
+        assert_eq!(
+            "-10%r".parse::<LineWidth>().unwrap_err(),
+            ParseLengthError::Negative("-10%r".to_string())
+        );
+        assert_eq!(
+            "mm".parse::<Length>(),
+            Err(ParseLengthError::Number("mm".to_string()))
+        );
     }
 }
@@ -0,0 +1,111 @@
+//! Monitor properties used to resolve sizes.
+
+use log::{debug, warn};
+use std::time::Duration;
+use winit::monitor::MonitorHandle;
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt;
+
+/// Pixel density assumed when the physical size is unknown (96 DPI)
+pub const DEFAULT_PIXELS_PER_MM: f64 = 96.0 / 25.4;
+
+/// Longer side assumed when no monitor is found (Full HD) \[px\]
+pub const DEFAULT_LONGER_SIDE: u32 = 1920;
+
+/// Longer side of `monitor`, or `DEFAULT_LONGER_SIDE` without one \[px\]
+pub fn longer_side(monitor: Option<&MonitorInfo>) -> u32 {
+    monitor.map_or(DEFAULT_LONGER_SIDE, MonitorInfo::longer_side)
+}
+
+/// Frame period of `monitor`, if it reports its refresh rate.
+pub fn refresh_period(monitor: &MonitorHandle) -> Option<Duration> {
+    monitor
+        .refresh_rate_millihertz()
+        .map(|mhz| Duration::from_secs_f64(1000.0 / mhz as f64))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// Size \[px\]
+    pub size: (u32, u32),
+    pub scale_factor: f64,
+    /// Physical size reported by RandR \[mm\]
+    pub size_mm: Option<(u32, u32)>,
+}
+
+impl MonitorInfo {
+    /// Reads the monitor properties, asking RandR for the physical size if `with_size_mm` is set.
+    pub fn new(monitor: &MonitorHandle, with_size_mm: bool) -> Self {
+        let size = monitor.size();
+        let position = monitor.position();
+        let size_mm = if with_size_mm {
+            randr_size_mm(monitor.name().as_deref(), (position.x, position.y))
+        } else {
+            None
+        };
+
+        Self {
+            size: (size.width, size.height),
+            scale_factor: monitor.scale_factor(),
+            size_mm,
+        }
+    }
+
+    pub fn longer_side(&self) -> u32 {
+        std::cmp::max(self.size.0, self.size.1)
+    }
+
+    pub fn pixels_per_mm(&self) -> Option<f64> {
+        match self.size_mm {
+            Some((w, _)) if w > 0 => Some(self.size.0 as f64 / w as f64),
+            _ => None,
+        }
+    }
+}
+
+/// Physical size of the RandR output named `name`, or of the one at `position`.
+fn randr_size_mm(name: Option<&str>, position: (i32, i32)) -> Option<(u32, u32)> {
+    match query_randr_size_mm(name, position) {
+        Ok(size_mm) => {
+            debug!("RandR: output={:?}, size_mm={:?}", name, size_mm);
+            size_mm
+        }
+        Err(e) => {
+            warn!("Cannot query the physical monitor size: {}", e);
+            None
+        }
+    }
+}
+
+fn query_randr_size_mm(
+    name: Option<&str>,
+    position: (i32, i32),
+) -> Result<Option<(u32, u32)>, Box<dyn std::error::Error>> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+
+    let mut by_position = None;
+    for output in resources.outputs {
+        let info = conn
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+        if info.crtc == 0 || info.mm_width == 0 || info.mm_height == 0 {
+            continue;
+        }
+
+        let size_mm = (info.mm_width, info.mm_height);
+        if name == Some(String::from_utf8_lossy(&info.name).as_ref()) {
+            return Ok(Some(size_mm));
+        }
+
+        let crtc = conn
+            .randr_get_crtc_info(info.crtc, resources.config_timestamp)?
+            .reply()?;
+        if (crtc.x as i32, crtc.y as i32) == position {
+            by_position = by_position.or(Some(size_mm));
+        }
+    }
+
+    Ok(by_position)
+}
@@ -0,0 +1,113 @@
+//! Beacon window on winit, drawn with softbuffer.
+
+use crate::error::Error;
+use crate::raster::DrawBuffer;
+use crate::transparency::{BoundingShape, Transparency};
+use crate::xserver::XServer;
+use log::warn;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::ActiveEventLoop;
+use winit::platform::x11::WindowAttributesExtX11;
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::window::{Window, WindowId};
+
+pub struct OverlayWindow {
+    window: Rc<Window>,
+    surface: softbuffer::Surface<Rc<Window>, Rc<Window>>,
+    _context: softbuffer::Context<Rc<Window>>,
+    /// Cuts the window down to the drawn pixels (no compositing manager)
+    bounding_shape: Option<BoundingShape>,
+}
+
+impl OverlayWindow {
+    /// Opens a click-through override-redirect window.
+    ///
+    /// `server` is only used to shape the window.
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        server: Option<&Rc<XServer>>,
+        position: (i32, i32),
+        size: (u32, u32),
+        transparency: Transparency,
+    ) -> Result<Self, Error> {
+        let attr = Window::default_attributes()
+            .with_transparent(true)
+            .with_decorations(false)
+            .with_inner_size(PhysicalSize::new(size.0, size.1))
+            .with_position(PhysicalPosition::new(position.0, position.1))
+            // X11
+            .with_override_redirect(true);
+
+        let window = Rc::new(event_loop.create_window(attr)?);
+        // Clicks during the animation go to the window underneath (empty XShape input region).
+        if let Err(e) = window.set_cursor_hittest(false) {
+            warn!("Cannot make the window click-through: {}", e);
+        }
+
+        let bounding_shape = match (transparency, server) {
+            (Transparency::Shape, Some(server)) => x11_window_id(&window)
+                .map(|id| BoundingShape::new(server.clone(), id))
+                .inspect_err(|e| warn!("Cannot shape the window: {}", e))
+                .ok(),
+            _ => None,
+        };
+        let context = softbuffer::Context::new(window.clone())?;
+        let surface = softbuffer::Surface::new(&context, window.clone())?;
+
+        Ok(Self {
+            window,
+            surface,
+            _context: context,
+            bounding_shape,
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub fn set_position(&self, position: (i32, i32)) {
+        self.window
+            .set_outer_position(PhysicalPosition::new(position.0, position.1));
+    }
+
+    pub fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    /// Always false: winit reports redraws as `WindowEvent::RedrawRequested`.
+    pub fn take_pending_redraw(&self) -> bool {
+        false
+    }
+
+    /// Lets `draw` fill the next frame and shows it.
+    pub fn draw(&mut self, draw: impl FnOnce(&mut DrawBuffer)) -> Result<(), Error> {
+        let size = self.window.inner_size();
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return Ok(());
+        };
+        self.surface.resize(width, height)?;
+
+        let mut buffer = self.surface.buffer_mut()?;
+        draw(&mut DrawBuffer::new(&mut buffer, size.width, size.height));
+
+        if let Some(bounding_shape) = &self.bounding_shape {
+            bounding_shape.update(&buffer, size.width);
+        }
+        buffer.present()?;
+        Ok(())
+    }
+}
+
+/// X11 id of `window`.
+fn x11_window_id(window: &Window) -> Result<u32, Box<dyn std::error::Error>> {
+    match window.window_handle()?.as_raw() {
+        RawWindowHandle::Xlib(handle) => Ok(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Ok(handle.window.get()),
+        _ => Err("not an X11 window".into()),
+    }
+}
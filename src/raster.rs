@@ -0,0 +1,329 @@
+//! Rasterization of beacon frames into pixel buffers, independent of any window.
+
+use crate::Mode;
+use crate::animation::Layer;
+use crate::argb;
+use crate::shape::Shape;
+use log::debug;
+
+/// Everything one frame shows.
+pub struct Frame<'a> {
+    pub mode: &'a Mode,
+    pub shape: &'a dyn Shape,
+    /// Layers scaled to pixels, from back to front
+    pub layers: &'a [Layer],
+    /// Opacity of the whole frame
+    pub opacity: f32,
+    pub color_argb: u32,
+    pub edge_color_argb: u32,
+    /// Cursor position relative to the buffer (full-monitor modes)
+    pub center: (i32, i32),
+    /// Radius before animation \[px\]
+    pub radius: u32,
+}
+
+/// Frame pixels of a beacon window.
+///
+/// 0xAA RR GG BB (premultiplied), row by row.
+pub struct DrawBuffer<'a> {
+    pixels: &'a mut [u32],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> DrawBuffer<'a> {
+    pub fn new(pixels: &'a mut [u32], width: u32, height: u32) -> Self {
+        Self {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Clears the buffer and draws `frame`.
+    pub fn draw_frame(&mut self, frame: &Frame) {
+        let color_argb = argb::scale(frame.color_argb, frame.opacity);
+        let edge_color_argb = argb::scale(frame.edge_color_argb, frame.opacity);
+
+        // Full-monitor modes only follow the front layer.
+        let front = frame.layers.last().copied().unwrap_or(Layer {
+            radius: 0.0,
+            line_width: 0.0,
+            opacity: 0.0,
+        });
+
+        match frame.mode {
+            Mode::Beacon => self.draw_shape(frame.shape, frame.layers, color_argb, edge_color_argb),
+            Mode::Spotlight(spotlight) => self.draw_spotlight(
+                frame.center,
+                front.radius,
+                spotlight.softness,
+                argb::scale(spotlight.dim_argb, front.opacity * frame.opacity),
+            ),
+            Mode::Crosshair => {
+                // Lines fade as the beacon radius would shrink.
+                let opacity = front.opacity * (front.radius / frame.radius.max(1) as f32);
+                self.draw_crosshair(
+                    frame.center,
+                    front.line_width,
+                    argb::scale(color_argb, opacity),
+                    argb::scale(edge_color_argb, opacity),
+                )
+            }
+        }
+    }
+
+    /// Draws `layers` of `shape` centered in the window, from back to front.
+    fn draw_shape(
+        &mut self,
+        shape: &dyn Shape,
+        layers: &[Layer],
+        color_argb: u32,
+        edge_color_argb: u32,
+    ) {
+        debug!(
+            "Draw shape: layers={}, color={:#x}, edge_color={:#x}",
+            layers.len(),
+            color_argb,
+            edge_color_argb
+        );
+
+        let (w, h) = (self.width, self.height);
+        let buffer = &mut *self.pixels;
+
+        let center_x = w as f32 / 2.0;
+        let center_y = h as f32 / 2.0;
+
+        for y in 0..h {
+            let idx_y = y * w;
+            let dy = y as f32 + 0.5 - center_y;
+            for x in 0..w {
+                let idx = (idx_y + x) as usize;
+                let dx = x as f32 + 0.5 - center_x;
+
+                // 0xAA RR GG BB (premultiplied)
+                buffer[idx] = layers.iter().fold(argb::TRANSPARENT, |pixel, layer| {
+                    let dist = shape.distance(dx, dy, layer.radius, layer.line_width);
+
+                    // The line area is outlined by a 1px edge band.
+                    let line_coverage = (0.5 - dist).clamp(0.0, 1.0);
+                    let edge_coverage = (1.5 - dist).clamp(0.0, 1.0) - line_coverage;
+
+                    let layer_pixel = argb::over(
+                        argb::scale(edge_color_argb, edge_coverage * layer.opacity),
+                        argb::scale(color_argb, line_coverage * layer.opacity),
+                    );
+                    argb::over(layer_pixel, pixel)
+                });
+            }
+        }
+    }
+
+    /// Fills the whole window with `dim_argb` except for a hole of `radius` around `center`.
+    fn draw_spotlight(&mut self, center: (i32, i32), radius: f32, softness: u32, dim_argb: u32) {
+        debug!(
+            "Draw spotlight: center={:?}, radius={:.1}px, softness={}px, dim_color={:#x}",
+            center, radius, softness, dim_argb
+        );
+
+        let (w, h) = (self.width, self.height);
+        let buffer = &mut *self.pixels;
+        buffer.fill(dim_argb);
+
+        let center_x = center.0 as f32 + 0.5;
+        let center_y = center.1 as f32 + 0.5;
+        let feather = softness.max(1) as f32;
+
+        // Only pixels within `reach` of the center differ from the dimmed fill.
+        let reach = radius + feather;
+        let y_begin = (center_y - reach).floor().clamp(0.0, h as f32) as u32;
+        let y_end = (center_y + reach).ceil().clamp(0.0, h as f32) as u32;
+
+        for y in y_begin..y_end {
+            let idx_y = y * w;
+            let dy = y as f32 + 0.5 - center_y;
+            let half_span = (reach * reach - dy * dy).max(0.0).sqrt();
+            let x_begin = (center_x - half_span).floor().clamp(0.0, w as f32) as u32;
+            let x_end = (center_x + half_span).ceil().clamp(0.0, w as f32) as u32;
+
+            for x in x_begin..x_end {
+                let idx = (idx_y + x) as usize;
+                let dx = x as f32 + 0.5 - center_x;
+                let dist = dx.hypot(dy);
+
+                let opacity = ((dist - radius) / feather + 0.5).clamp(0.0, 1.0);
+                buffer[idx] = argb::scale(dim_argb, opacity);
+            }
+        }
+    }
+
+    /// Draws a horizontal and a vertical line through `center`, each spanning the whole window.
+    fn draw_crosshair(
+        &mut self,
+        center: (i32, i32),
+        line_width: f32,
+        color_argb: u32,
+        edge_color_argb: u32,
+    ) {
+        debug!(
+            "Draw crosshair: center={:?}, line_width={:.1}px, color={:#x}, edge_color={:#x}",
+            center, line_width, color_argb, edge_color_argb
+        );
+
+        let (w, h) = (self.width, self.height);
+        let buffer = &mut *self.pixels;
+        buffer.fill(argb::TRANSPARENT);
+
+        let center_x = center.0 as f32 + 0.5;
+        let center_y = center.1 as f32 + 0.5;
+        let half_width = line_width / 2.0;
+
+        // (line, line + edge) coverage of a pixel at `offset` from a line's center.
+        let coverage = |offset: f32| {
+            let dist = offset.abs() - half_width;
+            ((0.5 - dist).clamp(0.0, 1.0), (1.5 - dist).clamp(0.0, 1.0))
+        };
+        let pixel = |x: u32, y: u32| {
+            let (line_h, total_h) = coverage(y as f32 + 0.5 - center_y);
+            let (line_v, total_v) = coverage(x as f32 + 0.5 - center_x);
+            let line_coverage = line_h.max(line_v);
+            let edge_coverage = total_h.max(total_v) - line_coverage;
+
+            argb::over(
+                argb::scale(edge_color_argb, edge_coverage),
+                argb::scale(color_argb, line_coverage),
+            )
+        };
+
+        // Only the bands around both lines are not transparent.
+        let band = |c: f32, size: u32| {
+            let begin = (c - half_width - 2.0).floor().clamp(0.0, size as f32) as u32;
+            let end = (c + half_width + 2.0).ceil().clamp(0.0, size as f32) as u32;
+            begin..end
+        };
+        let rows = band(center_y, h);
+        let columns = band(center_x, w);
+
+        for y in 0..h {
+            let idx_y = y * w;
+            if rows.contains(&y) {
+                for x in 0..w {
+                    buffer[(idx_y + x) as usize] = pixel(x, y);
+                }
+            } else {
+                for x in columns.clone() {
+                    buffer[(idx_y + x) as usize] = pixel(x, y);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Animation, Easing, STEPS, Style};
+    use crate::shape::Ring;
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// Largest accepted difference of a color channel
+    const TOLERANCE: u8 = 2;
+
+    fn golden_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("golden")
+            .join(format!("{}.png", name))
+    }
+
+    fn write_png(name: &str, rgba: &[u8], width: u32, height: u32) {
+        let file = File::create(golden_path(name)).unwrap();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rgba).unwrap();
+    }
+
+    fn read_png(name: &str) -> (Vec<u8>, u32, u32) {
+        let path = golden_path(name);
+        let file = File::open(&path)
+            .unwrap_or_else(|e| panic!("{}: {} (UPDATE_GOLDEN=1 creates it)", path.display(), e));
+        let mut reader = png::Decoder::new(BufReader::new(file)).read_info().unwrap();
+        let mut rgba = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut rgba).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba, "{}", name);
+        rgba.truncate(info.buffer_size());
+        (rgba, info.width, info.height)
+    }
+
+    /// Compares `pixels` with the reference image `name`, or replaces it with UPDATE_GOLDEN set.
+    fn assert_golden(name: &str, pixels: &[u32], width: u32, height: u32) {
+        let rgba: Vec<u8> = pixels.iter().flat_map(|&p| argb::to_rgba8(p)).collect();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            write_png(name, &rgba, width, height);
+            return;
+        }
+
+        let (expected, expected_width, expected_height) = read_png(name);
+        assert_eq!(
+            (width, height),
+            (expected_width, expected_height),
+            "{}",
+            name
+        );
+        let differing = rgba
+            .chunks_exact(4)
+            .zip(expected.chunks_exact(4))
+            .filter(|(a, b)| a.iter().zip(*b).any(|(x, y)| x.abs_diff(*y) > TOLERANCE))
+            .count();
+        assert_eq!(differing, 0, "{}: pixels differ beyond the tolerance", name);
+    }
+
+    /// Every frame of the default animation (ring, steps, shrink, orangered on gray).
+    fn default_frames(radius: u32, line_width: u32) -> Vec<Vec<u32>> {
+        let interval = Duration::from_millis(70);
+        let animation = Animation::new(interval * STEPS, Easing::Steps, Style::Shrink, 3, None);
+        let color_argb = argb::from_color(&csscolorparser::parse("orangered").unwrap());
+        let edge_color_argb = argb::from_color(&csscolorparser::parse("gray").unwrap());
+        let size = radius * 2;
+
+        (0..STEPS)
+            .map(|step| {
+                let progress = animation.progress(interval * step).unwrap();
+                let layers: Vec<Layer> = animation
+                    .layers(progress)
+                    .iter()
+                    .map(|layer| layer.scaled(radius as f32, line_width as f32))
+                    .collect();
+
+                let mut pixels = vec![argb::TRANSPARENT; (size * size) as usize];
+                DrawBuffer::new(&mut pixels, size, size).draw_frame(&Frame {
+                    mode: &Mode::Beacon,
+                    shape: &Ring,
+                    layers: &layers,
+                    opacity: 1.0,
+                    color_argb,
+                    edge_color_argb,
+                    center: (radius as i32, radius as i32),
+                    radius,
+                });
+                pixels
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_animation_matches_golden_images() {
+        for (radius, line_width) in [(30, 3), (30, 8), (60, 4), (60, 12)] {
+            let size = radius * 2;
+            for (step, pixels) in default_frames(radius, line_width).iter().enumerate() {
+                let name = format!("ring-r{}-w{}-{}", radius, line_width, step);
+                assert_golden(&name, pixels, size, size);
+            }
+        }
+    }
+}
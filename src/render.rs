@@ -0,0 +1,251 @@
+//! `render`: writes the animation to image files instead of showing it.
+//!
+//! Frames follow the same schedule as on screen, one per `Animation::frame_interval`,
+//! except for GIF, which can only store delays in whole centiseconds.
+
+use crate::animation::Layer;
+use crate::error::Error;
+use crate::monitor::MonitorInfo;
+use crate::raster::{DrawBuffer, Frame};
+use crate::{Mode, Settings, argb};
+use log::{debug, info};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RenderFormat {
+    /// One PNG file per frame, in the output directory
+    Png,
+    /// A single animated PNG
+    Apng,
+    /// A single animated GIF (transparency is either on or off per pixel)
+    Gif,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    pub output: PathBuf,
+    pub format: RenderFormat,
+    /// Monitor the sizes are resolved for
+    pub monitor: MonitorInfo,
+    /// Frame period of the smooth easings
+    pub refresh: Duration,
+    pub background_argb: u32,
+}
+
+/// Straight RGBA frames of the same size.
+struct Frames {
+    width: u32,
+    height: u32,
+    /// Time each frame is shown
+    delay: Duration,
+    rgba: Vec<Vec<u8>>,
+}
+
+/// Shortest GIF delay that viewers play as written \[cs\]
+const MIN_GIF_DELAY_CS: u32 = 2;
+
+/// Renders one pass of the animation as `config` says.
+pub fn render(settings: &Settings, config: &RenderConfig) -> Result<(), Error> {
+    let frames = draw_frames(settings, config);
+    if frames.rgba.is_empty() {
+        return Err(Error::Render("the animation has no frames".into()));
+    }
+    info!(
+        "Render: frames={}, size={}x{}, delay={:?}, output={}",
+        frames.rgba.len(),
+        frames.width,
+        frames.height,
+        frames.delay,
+        config.output.display()
+    );
+
+    let result = match config.format {
+        RenderFormat::Png => write_png_frames(&config.output, &frames),
+        RenderFormat::Apng => write_apng(&config.output, &frames),
+        RenderFormat::Gif => write_gif(&config.output, &frames),
+    };
+    result.map_err(Error::Render)
+}
+
+fn draw_frames(settings: &Settings, config: &RenderConfig) -> Frames {
+    let monitor = Some(&config.monitor);
+    let radius = settings.radius(monitor);
+    let line_width = settings.line_width(monitor);
+    let appearance = settings.appearance();
+    let animation = &appearance.animation;
+
+    // The same window the beacon would open, with the cursor in the middle.
+    let (width, height) = match appearance.mode {
+        Mode::Beacon => (radius * 2, radius * 2),
+        Mode::Spotlight(_) | Mode::Crosshair => config.monitor.size,
+    };
+    let center = ((width / 2) as i32, (height / 2) as i32);
+
+    let interval = match config.format {
+        RenderFormat::Png | RenderFormat::Apng => animation.frame_interval(config.refresh),
+        RenderFormat::Gif => gif_interval(animation.frame_interval(config.refresh)),
+    };
+    let rgba = animation
+        .frame_times(interval)
+        .filter_map(|elapsed| {
+            let progress = animation.progress(elapsed)?;
+            let layers: Vec<Layer> = animation
+                .layers(progress)
+                .iter()
+                .map(|layer| layer.scaled(radius as f32, line_width as f32))
+                .collect();
+            debug!("Frame: elapsed={:?}, progress={}", elapsed, progress);
+
+            let mut pixels = vec![argb::TRANSPARENT; width as usize * height as usize];
+            DrawBuffer::new(&mut pixels, width, height).draw_frame(&Frame {
+                mode: &appearance.mode,
+                shape: settings.shape(),
+                layers: &layers,
+                opacity: animation.opacity(elapsed),
+                color_argb: appearance.color_argb,
+                edge_color_argb: appearance.edge_color_argb,
+                center,
+                radius,
+            });
+            Some(
+                pixels
+                    .iter()
+                    .flat_map(|&pixel| argb::to_rgba8(argb::over(pixel, config.background_argb)))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    Frames {
+        width,
+        height,
+        delay: interval,
+        rgba,
+    }
+}
+
+/// `interval` rounded to whole centiseconds, but at least `MIN_GIF_DELAY_CS`.
+fn gif_interval(interval: Duration) -> Duration {
+    let cs = ((interval.as_secs_f64() * 100.0).round() as u32).max(MIN_GIF_DELAY_CS);
+    Duration::from_millis(cs as u64 * 10)
+}
+
+/// `delay` as the closest fraction of seconds that fits an APNG frame control chunk.
+fn delay_fraction(delay: Duration) -> (u16, u16) {
+    // Continued fraction convergents, until the next one no longer fits.
+    let (mut num, mut den) = (1u64, 0u64);
+    let (mut prev_num, mut prev_den) = (0u64, 1u64);
+    let mut x = delay.as_secs_f64();
+    loop {
+        let a = x.floor();
+        let (next_num, next_den) = (a as u64 * num + prev_num, a as u64 * den + prev_den);
+        if next_num > u16::MAX as u64 || next_den > u16::MAX as u64 {
+            break;
+        }
+        (prev_num, prev_den, num, den) = (num, den, next_num, next_den);
+        if x - a < 1e-9 {
+            break;
+        }
+        x = 1.0 / (x - a);
+    }
+    if den == 0 {
+        return (u16::MAX, 1);
+    }
+    (num as u16, den as u16)
+}
+
+fn png_encoder(
+    path: &Path,
+    frames: &Frames,
+) -> std::io::Result<png::Encoder<'static, BufWriter<File>>> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frames.width, frames.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    Ok(encoder)
+}
+
+/// `frame-0000.png`, `frame-0001.png`, ... in the directory `dir`.
+fn write_png_frames(dir: &Path, frames: &Frames) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+    for (i, rgba) in frames.rgba.iter().enumerate() {
+        let path = dir.join(format!("frame-{:04}.png", i));
+        let mut writer = png_encoder(&path, frames)?.write_header()?;
+        writer.write_image_data(rgba)?;
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+/// An animated PNG looping forever.
+fn write_apng(path: &Path, frames: &Frames) -> Result<(), Box<dyn std::error::Error>> {
+    let mut encoder = png_encoder(path, frames)?;
+    encoder.set_animated(frames.rgba.len() as u32, 0)?;
+    let (num, den) = delay_fraction(frames.delay);
+    encoder.set_frame_delay(num, den)?;
+
+    let mut writer = encoder.write_header()?;
+    for rgba in &frames.rgba {
+        writer.write_image_data(rgba)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// An animated GIF looping forever.
+fn write_gif(path: &Path, frames: &Frames) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = (
+        u16::try_from(frames.width).map_err(|_| "too wide for a GIF")?,
+        u16::try_from(frames.height).map_err(|_| "too high for a GIF")?,
+    );
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    // GIF delays are in units of 10 ms, which `gif_interval` already rounded to.
+    let delay = (frames.delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for rgba in &frames.rgba {
+        let mut rgba = rgba.clone();
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+        frame.delay = delay;
+        // Transparent pixels must not keep the previous frame.
+        frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apng_delay_is_exact() {
+        assert_eq!(delay_fraction(Duration::from_secs_f64(1.0 / 60.0)), (1, 60));
+        assert_eq!(
+            delay_fraction(Duration::from_secs_f64(1.0 / 144.0)),
+            (1, 144)
+        );
+        assert_eq!(delay_fraction(Duration::from_millis(70)), (7, 100));
+        assert_eq!(delay_fraction(Duration::ZERO), (0, 1));
+    }
+
+    #[test]
+    fn gif_delay_is_whole_centiseconds() {
+        assert_eq!(
+            gif_interval(Duration::from_secs_f64(1.0 / 60.0)),
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            gif_interval(Duration::from_millis(70)),
+            Duration::from_millis(70)
+        );
+        assert_eq!(
+            gif_interval(Duration::from_millis(44)),
+            Duration::from_millis(40)
+        );
+    }
+}
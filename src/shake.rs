@@ -0,0 +1,218 @@
+//! Shake-to-locate: shows the beacon when the mouse is shaken.
+
+use crate::cursor::CursorConfig;
+use crate::daemon::Request;
+use log::{debug, error, info};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use winit::event_loop::EventLoopProxy;
+
+#[derive(Debug, Clone)]
+pub struct ShakeConfig {
+    /// Time between pointer samples
+    pub sample_interval: Duration,
+    /// Time window in which the reversals have to happen
+    pub window: Duration,
+    /// Direction reversals required within `window`
+    pub reversals: usize,
+    /// Minimum travel between two reversals \[px\]
+    pub min_distance: u32,
+    /// Minimum time between two detections
+    pub cooldown: Duration,
+}
+
+/// Movement along one axis.
+#[derive(Debug, Default)]
+struct Axis {
+    /// -1, 0 (not moved yet) or 1
+    direction: i32,
+    /// Distance moved in `direction` since the last reversal
+    travel: u32,
+}
+
+impl Axis {
+    /// Returns true when `delta` reverses a movement of at least `min_distance`.
+    fn feed(&mut self, delta: i32, min_distance: u32) -> bool {
+        if delta == 0 {
+            return false;
+        }
+
+        let direction = delta.signum();
+        if direction == self.direction {
+            self.travel += delta.unsigned_abs();
+            return false;
+        }
+
+        let reversed = self.direction != 0 && self.travel >= min_distance;
+        self.direction = direction;
+        self.travel = delta.unsigned_abs();
+        reversed
+    }
+}
+
+/// Detects shakes from a stream of timestamped pointer positions.
+///
+/// Timestamps are relative to an arbitrary origin and must not decrease.
+#[derive(Debug)]
+pub struct ShakeDetector {
+    config: ShakeConfig,
+    last_position: Option<(i32, i32)>,
+    x: Axis,
+    y: Axis,
+    reversal_times: VecDeque<Duration>,
+    last_detection: Option<Duration>,
+}
+
+impl ShakeDetector {
+    pub fn new(config: ShakeConfig) -> Self {
+        Self {
+            config,
+            last_position: None,
+            x: Axis::default(),
+            y: Axis::default(),
+            reversal_times: VecDeque::new(),
+            last_detection: None,
+        }
+    }
+
+    /// Feeds a pointer sample and returns true when it completes a shake.
+    pub fn feed(&mut self, time: Duration, position: (i32, i32)) -> bool {
+        let Some(last_position) = self.last_position.replace(position) else {
+            return false;
+        };
+
+        let min_distance = self.config.min_distance;
+        let reversed_x = self.x.feed(position.0 - last_position.0, min_distance);
+        let reversed_y = self.y.feed(position.1 - last_position.1, min_distance);
+        if reversed_x || reversed_y {
+            self.reversal_times.push_back(time);
+        }
+
+        while let Some(&oldest) = self.reversal_times.front() {
+            if time.saturating_sub(oldest) <= self.config.window {
+                break;
+            }
+            self.reversal_times.pop_front();
+        }
+
+        if self.reversal_times.len() < self.config.reversals {
+            return false;
+        }
+
+        if let Some(last_detection) = self.last_detection
+            && time.saturating_sub(last_detection) < self.config.cooldown
+        {
+            return false;
+        }
+
+        self.reversal_times.clear();
+        self.last_detection = Some(time);
+        true
+    }
+}
+
+/// Samples the pointer forever, asking the event loop to show the beacon on every shake.
+pub fn watch(config: ShakeConfig, cursor_config: CursorConfig, proxy: EventLoopProxy<Request>) {
+    info!("Watch for shakes: {:?}", config);
+
+    let mut cursor = match cursor_config.open() {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            error!("Cannot read the cursor position: {}", e);
+            return;
+        }
+    };
+    let sample_interval = config.sample_interval;
+    let mut detector = ShakeDetector::new(config);
+    let origin = Instant::now();
+
+    loop {
+        if let Some(position) = cursor.position()
+            && detector.feed(origin.elapsed(), position)
+        {
+            debug!("Shake detected: {:?}", position);
+            if proxy.send_event(Request::Show).is_err() {
+                return;
+            }
+        }
+
+        std::thread::sleep(sample_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pointer sample interval of the recorded streams \[ms\]
+    const SAMPLE_MS: u64 = 10;
+
+    fn config() -> ShakeConfig {
+        ShakeConfig {
+            sample_interval: Duration::from_millis(SAMPLE_MS),
+            window: Duration::from_millis(600),
+            reversals: 4,
+            min_distance: 40,
+            cooldown: Duration::from_millis(1000),
+        }
+    }
+
+    /// Feeds horizontal positions one sample apart and returns the detection times \[ms\].
+    fn detections(xs: &[i32]) -> Vec<u64> {
+        let mut detector = ShakeDetector::new(config());
+        (0..)
+            .map(|i| i * SAMPLE_MS)
+            .zip(xs)
+            .filter(|&(ms, &x)| detector.feed(Duration::from_millis(ms), (x, 300)))
+            .map(|(ms, _)| ms)
+            .collect()
+    }
+
+    /// A quick back-and-forth between 0 and 120 px, seven strokes long.
+    const SHAKE: &[i32] = &[
+        0, 40, 80, 120, 80, 40, 0, 40, 80, 120, 80, 40, 0, 40, 80, 120, 80, 40, 0, 40, 80, 120,
+    ];
+
+    /// `samples` samples without movement.
+    fn still(x: i32, samples: usize) -> Vec<i32> {
+        vec![x; samples]
+    }
+
+    #[test]
+    fn shake_fires_once() {
+        let stream = [SHAKE, &still(120, 50)].concat();
+        // Reversals at 120, 0, 120 and 0 px; the fourth one completes the shake.
+        assert_eq!(detections(&stream), vec![130]);
+    }
+
+    #[test]
+    fn straight_motion_never_fires() {
+        let stream: Vec<i32> = (0..200).map(|i| i * 15).collect();
+        assert!(detections(&stream).is_empty());
+    }
+
+    #[test]
+    fn jitter_below_min_distance_never_fires() {
+        let stream: Vec<i32> = (0..200).map(|i| 500 + (i % 2) * 6).collect();
+        assert!(detections(&stream).is_empty());
+    }
+
+    #[test]
+    fn slow_reversals_never_fire() {
+        // Strokes of 120 px over 300 ms: four reversals span 900 ms, more than the window.
+        let stroke: Vec<i32> = (0..30).map(|i| i * 4).collect();
+        let back: Vec<i32> = stroke.iter().rev().copied().collect();
+        let stream = [&stroke[..], &back, &stroke, &back, &stroke, &back].concat();
+        assert!(detections(&stream).is_empty());
+    }
+
+    #[test]
+    fn shake_within_cooldown_is_suppressed() {
+        let stream = [SHAKE, &still(120, 30), SHAKE, &still(120, 100), SHAKE].concat();
+        let times = detections(&stream);
+        // The second shake ends within the cooldown, the third starts after it.
+        assert_eq!(times.len(), 2, "{:?}", times);
+        assert_eq!(times[0], 130);
+        assert!(times[1] - times[0] >= 1000, "{:?}", times);
+    }
+}
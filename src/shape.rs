@@ -0,0 +1,146 @@
+//! Beacon shapes.
+//!
+//! A shape is described by a signed distance field centered on the cursor.
+//! `DrawBuffer` turns the distance into line/edge coverage, so every shape is
+//! anti-aliased and outlined with the edge color the same way.
+
+use std::f32::consts::SQRT_2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShapeKind {
+    /// Circle outline
+    Ring,
+    /// Filled circle
+    Disc,
+    /// Square outline
+    Square,
+    /// Diamond outline
+    Diamond,
+    /// Four arrows pointing at the cursor
+    Arrows,
+    /// Crosshair with a gap around the cursor
+    Crosshair,
+}
+
+pub trait Shape {
+    /// Signed distance from `(x, y)`, relative to the beacon center, to the line area.
+    /// Negative inside the line.
+    ///
+    /// `radius` is the outer extent of the shape including its edge.
+    fn distance(&self, x: f32, y: f32, radius: f32, line_width: f32) -> f32;
+}
+
+/// Distance to a stroke of width `line_width` centered on a contour with distance `d`.
+fn stroke(d: f32, line_width: f32) -> f32 {
+    d.abs() - line_width / 2.0
+}
+
+/// Radius of the contour whose stroke (plus edge) just fits inside `radius`.
+fn center_line(radius: f32, line_width: f32) -> f32 {
+    radius - 1.0 - line_width / 2.0
+}
+
+pub struct Ring;
+
+impl Shape for Ring {
+    fn distance(&self, x: f32, y: f32, radius: f32, line_width: f32) -> f32 {
+        let dist = x.hypot(y);
+        stroke(dist - center_line(radius, line_width), line_width)
+    }
+}
+
+pub struct Disc;
+
+impl Shape for Disc {
+    fn distance(&self, x: f32, y: f32, radius: f32, _line_width: f32) -> f32 {
+        x.hypot(y) - (radius - 1.0)
+    }
+}
+
+pub struct Square;
+
+impl Shape for Square {
+    fn distance(&self, x: f32, y: f32, radius: f32, line_width: f32) -> f32 {
+        let d = x.abs().max(y.abs()) - center_line(radius, line_width);
+        stroke(d, line_width)
+    }
+}
+
+pub struct Diamond;
+
+impl Shape for Diamond {
+    fn distance(&self, x: f32, y: f32, radius: f32, line_width: f32) -> f32 {
+        // Keep the stroke's vertices (not just its sides) inside the radius.
+        let vertex = radius - 1.0 - line_width / 2.0 * SQRT_2;
+        let d = (x.abs() + y.abs() - vertex) / SQRT_2;
+        stroke(d, line_width)
+    }
+}
+
+pub struct Arrows {
+    /// Arrow length relative to the radius
+    length: f32,
+}
+
+impl Arrows {
+    pub fn new(length: f32) -> Self {
+        Self {
+            length: length.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Shape for Arrows {
+    fn distance(&self, x: f32, y: f32, radius: f32, _line_width: f32) -> f32 {
+        // Fold all four arrows onto the one lying on the positive x axis.
+        let (along, across) = if x.abs() >= y.abs() {
+            (x.abs(), y.abs())
+        } else {
+            (y.abs(), x.abs())
+        };
+
+        let base = radius - 1.0;
+        let length = base * self.length;
+        let tip = base - length;
+        let half_width = length / 2.0;
+
+        // Side from the tip to the corner of the base; the inside is towards the axis.
+        let side_len = length.hypot(half_width);
+        let side = if side_len > 0.0 {
+            ((along - tip) * -half_width + across * length) / side_len
+        } else {
+            f32::INFINITY
+        };
+
+        (along - base).max(side)
+    }
+}
+
+pub struct Crosshair {
+    /// Gap around the center relative to the radius
+    gap: f32,
+}
+
+impl Crosshair {
+    pub fn new(gap: f32) -> Self {
+        Self {
+            gap: gap.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Shape for Crosshair {
+    fn distance(&self, x: f32, y: f32, radius: f32, line_width: f32) -> f32 {
+        let end = radius - 1.0;
+        let gap = end * self.gap;
+
+        let arm = |along: f32, across: f32| {
+            let along = along.abs();
+            (across.abs() - line_width / 2.0)
+                .max(gap - along)
+                .max(along - end)
+        };
+
+        arm(x, y).min(arm(y, x))
+    }
+}
@@ -0,0 +1,127 @@
+//! See-through beacon windows with and without a compositing manager.
+//!
+//! A compositor blends the transparent pixels of an ARGB window with what is underneath.
+//! Without one they show as black, so the window is instead cut down to its opaque pixels
+//! with the X Shape extension.
+
+use crate::xserver::XServer;
+use log::{debug, info, warn};
+use std::rc::Rc;
+use x11rb::connection::Connection;
+use x11rb::protocol::shape::{ConnectionExt as _, SK, SO};
+use x11rb::protocol::xproto::{ClipOrdering, ConnectionExt as _, Rectangle};
+
+/// Pixels at least this opaque are kept in a shaped window
+const SHAPE_ALPHA_THRESHOLD: u32 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Transparency {
+    /// Composite if a compositing manager is running, otherwise shape
+    Auto,
+    /// Let the compositing manager blend the window
+    Composite,
+    /// Cut the window down to the drawn pixels
+    Shape,
+}
+
+impl Transparency {
+    /// Resolves `Auto` by looking for a compositing manager on `server`.
+    ///
+    /// Done once at startup; the result holds for every beacon.
+    pub fn resolve(self, server: Option<&XServer>) -> Self {
+        if self != Transparency::Auto {
+            return self;
+        }
+
+        let resolved = match server
+            .ok_or("no X connection".into())
+            .and_then(compositor_running)
+        {
+            Ok(true) => Transparency::Composite,
+            Ok(false) => Transparency::Shape,
+            Err(e) => {
+                warn!("Cannot detect a compositing manager: {}", e);
+                Transparency::Composite
+            }
+        };
+        info!("Transparency: {:?}", resolved);
+        resolved
+    }
+}
+
+/// Whether a compositing manager owns the `_NET_WM_CM_S<screen>` selection.
+fn compositor_running(server: &XServer) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = server.conn();
+    let name = format!("_NET_WM_CM_S{}", server.screen_num());
+    let atom = conn.intern_atom(false, name.as_bytes())?.reply()?.atom;
+    let owner = conn.get_selection_owner(atom)?.reply()?.owner;
+    debug!(
+        "Compositing manager: selection={}, owner={:#x}",
+        name, owner
+    );
+    Ok(owner != x11rb::NONE)
+}
+
+/// Bounding region of a window, following what is drawn into it.
+pub struct BoundingShape {
+    server: Rc<XServer>,
+    window: u32,
+}
+
+impl BoundingShape {
+    /// Shapes the X11 window `window` over the shared connection.
+    pub fn new(server: Rc<XServer>, window: u32) -> Self {
+        Self { server, window }
+    }
+
+    /// Limits the window to the opaque pixels of a `width` pixels wide frame.
+    pub fn update(&self, pixels: &[u32], width: u32) {
+        let rectangles = opaque_spans(pixels, width);
+        debug!("Shape: rectangles={}", rectangles.len());
+
+        self.server.discard_events();
+        let conn = self.server.conn();
+        let result = conn
+            .shape_rectangles(
+                SO::SET,
+                SK::BOUNDING,
+                ClipOrdering::Y_SORTED,
+                self.window,
+                0,
+                0,
+                &rectangles,
+            )
+            .and_then(|_| conn.flush());
+        if let Err(e) = result {
+            warn!("Cannot shape the window: {}", e);
+        }
+    }
+}
+
+/// Runs of opaque pixels in each row, as one pixel high rectangles.
+fn opaque_spans(pixels: &[u32], width: u32) -> Vec<Rectangle> {
+    let mut rectangles = Vec::new();
+
+    for (y, row) in pixels.chunks_exact(width as usize).enumerate() {
+        let mut begin = None;
+        // A transparent sentinel closes a run that reaches the right edge.
+        for (x, &pixel) in row.iter().chain([&0]).enumerate() {
+            let opaque = pixel >> 24 >= SHAPE_ALPHA_THRESHOLD;
+            match (begin, opaque) {
+                (None, true) => begin = Some(x),
+                (Some(b), false) => {
+                    rectangles.push(Rectangle {
+                        x: b as i16,
+                        y: y as i16,
+                        width: (x - b) as u16,
+                        height: 1,
+                    });
+                    begin = None;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    rectangles
+}
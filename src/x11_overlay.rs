@@ -0,0 +1,284 @@
+//! Beacon window created directly with x11rb (`native-x11` feature).
+//!
+//! The window uses a 32-bit ARGB visual and frames are uploaded with MIT-SHM,
+//! or with plain PutImage requests if the server does not offer it.
+//! The event loop, monitors and requests stay on winit.
+
+use crate::error::Error;
+use crate::raster::DrawBuffer;
+use crate::transparency::{BoundingShape, Transparency};
+use crate::xserver::{ARGB_DEPTH, XServer};
+use log::{debug, info, warn};
+use memmap2::MmapMut;
+use std::cell::Cell;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+use std::rc::Rc;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::WindowId;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::shape::{ConnectionExt as _, SK, SO};
+use x11rb::protocol::shm::ConnectionExt as _;
+use x11rb::protocol::xproto::{
+    ClipOrdering, ConfigureWindowAux, ConnectionExt as _, CreateGCAux, CreateWindowAux,
+    ImageFormat, ImageOrder, WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+
+/// Size of a PutImage request without its data \[bytes\]
+const PUT_IMAGE_HEADER: usize = 24;
+
+pub struct OverlayWindow {
+    server: Rc<XServer>,
+    window: u32,
+    gc: u32,
+    size: (u32, u32),
+    pixels: Vec<u32>,
+    /// Shared memory the server reads frames from, if MIT-SHM is available
+    shm: Option<ShmSegment>,
+    big_endian: bool,
+    /// Cuts the window down to the drawn pixels (no compositing manager)
+    bounding_shape: Option<BoundingShape>,
+    redraw_requested: Cell<bool>,
+}
+
+impl OverlayWindow {
+    /// Opens a click-through override-redirect window on the shared connection.
+    pub fn new(
+        _event_loop: &ActiveEventLoop,
+        server: Option<&Rc<XServer>>,
+        position: (i32, i32),
+        size: (u32, u32),
+        transparency: Transparency,
+    ) -> Result<Self, Error> {
+        Self::create(server, position, size, transparency).map_err(Error::Window)
+    }
+
+    fn create(
+        server: Option<&Rc<XServer>>,
+        position: (i32, i32),
+        size: (u32, u32),
+        transparency: Transparency,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let server = server.ok_or("no X connection")?;
+        let conn = server.conn();
+        let root = conn.setup().roots[server.screen_num()].root;
+        let argb_visual = server.argb_visual()?;
+        let big_endian = conn.setup().image_byte_order == ImageOrder::MSB_FIRST;
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            ARGB_DEPTH,
+            window,
+            root,
+            position.0 as i16,
+            position.1 as i16,
+            size.0 as u16,
+            size.1 as u16,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            argb_visual.visual,
+            &CreateWindowAux::new()
+                .background_pixel(0)
+                .border_pixel(0)
+                .colormap(argb_visual.colormap)
+                .override_redirect(1),
+        )?;
+        // Clicks during the animation go to the window underneath.
+        conn.shape_rectangles(
+            SO::SET,
+            SK::INPUT,
+            ClipOrdering::UNSORTED,
+            window,
+            0,
+            0,
+            &[],
+        )?;
+        conn.map_window(window)?;
+
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, window, &CreateGCAux::new())?;
+
+        let pixel_count = size.0 as usize * size.1 as usize;
+        let shm = match ShmSegment::new(conn, pixel_count * 4) {
+            Ok(shm) => Some(shm),
+            Err(e) => {
+                info!("MIT-SHM unavailable, using PutImage: {}", e);
+                None
+            }
+        };
+        conn.flush()?;
+        debug!("Native window: id={:#x}, shm={}", window, shm.is_some());
+
+        let bounding_shape = (transparency == Transparency::Shape)
+            .then(|| BoundingShape::new(server.clone(), window));
+
+        Ok(Self {
+            server: server.clone(),
+            window,
+            gc,
+            size,
+            pixels: vec![0; pixel_count],
+            shm,
+            big_endian,
+            bounding_shape,
+            redraw_requested: Cell::new(false),
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        WindowId::from(self.window as u64)
+    }
+
+    pub fn set_position(&self, position: (i32, i32)) {
+        let aux = ConfigureWindowAux::new().x(position.0).y(position.1);
+        let conn = self.server.conn();
+        let result = conn
+            .configure_window(self.window, &aux)
+            .and_then(|_| conn.flush());
+        if let Err(e) = result {
+            warn!("Cannot move the window: {}", e);
+        }
+    }
+
+    pub fn request_redraw(&self) {
+        self.redraw_requested.set(true);
+    }
+
+    /// Whether `request_redraw` was called since the last call.
+    /// Native windows get no redraw events, so the caller draws.
+    pub fn take_pending_redraw(&self) -> bool {
+        self.redraw_requested.take()
+    }
+
+    /// Lets `draw` fill the next frame and shows it.
+    pub fn draw(&mut self, draw: impl FnOnce(&mut DrawBuffer)) -> Result<(), Error> {
+        let (width, height) = self.size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        draw(&mut DrawBuffer::new(&mut self.pixels, width, height));
+
+        if let Some(bounding_shape) = &self.bounding_shape {
+            bounding_shape.update(&self.pixels, width);
+        }
+        self.upload().map_err(Error::Surface)
+    }
+
+    fn upload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.server.discard_events();
+        let conn = self.server.conn();
+        let (width, height) = (self.size.0 as u16, self.size.1 as u16);
+        let to_bytes = if self.big_endian {
+            u32::to_be_bytes
+        } else {
+            u32::to_le_bytes
+        };
+
+        if let Some(shm) = &mut self.shm {
+            for (bytes, &pixel) in shm.map.chunks_exact_mut(4).zip(&self.pixels) {
+                bytes.copy_from_slice(&to_bytes(pixel));
+            }
+            // Checking waits until the server has read the segment, so it can be reused.
+            conn.shm_put_image(
+                self.window,
+                self.gc,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                ARGB_DEPTH,
+                ImageFormat::Z_PIXMAP.into(),
+                false,
+                shm.seg,
+                0,
+            )?
+            .check()?;
+            return Ok(());
+        }
+
+        // Split the frame into bands of rows that fit into a request.
+        let row_bytes = width as usize * 4;
+        let max_rows = (conn.maximum_request_bytes() - PUT_IMAGE_HEADER) / row_bytes;
+        let rows_per_request = max_rows.clamp(1, height as usize);
+        let bytes: Vec<u8> = self.pixels.iter().flat_map(|&p| to_bytes(p)).collect();
+        for (i, band) in bytes.chunks(rows_per_request * row_bytes).enumerate() {
+            conn.put_image(
+                ImageFormat::Z_PIXMAP,
+                self.window,
+                self.gc,
+                width,
+                (band.len() / row_bytes) as u16,
+                0,
+                (i * rows_per_request) as i16,
+                0,
+                ARGB_DEPTH,
+                band,
+            )?;
+        }
+        conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for OverlayWindow {
+    /// Frees the window's resources, since the shared connection outlives it.
+    fn drop(&mut self) {
+        let conn = self.server.conn();
+        let result = (|| {
+            if let Some(shm) = &self.shm {
+                conn.shm_detach(shm.seg)?;
+            }
+            conn.free_gc(self.gc)?;
+            conn.destroy_window(self.window)?;
+            conn.flush()
+        })();
+        if let Err(e) = result {
+            warn!("Cannot destroy the window: {}", e);
+        }
+    }
+}
+
+/// MIT-SHM segment backed by an unlinked file in `/dev/shm`.
+struct ShmSegment {
+    seg: u32,
+    map: MmapMut,
+}
+
+impl ShmSegment {
+    fn new(conn: &RustConnection, len: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        // Passing the segment as a file descriptor needs MIT-SHM 1.2.
+        let version = conn.shm_query_version()?.reply()?;
+        if (version.major_version, version.minor_version) < (1, 2) {
+            return Err("MIT-SHM 1.2 is required".into());
+        }
+
+        let dir = PathBuf::from("/dev/shm");
+        let dir = if dir.is_dir() {
+            dir
+        } else {
+            std::env::temp_dir()
+        };
+        let path = dir.join(format!("cursor-beacon-{}", std::process::id()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        std::fs::remove_file(&path)?;
+        file.set_len(len as u64)?;
+
+        // SAFETY: the file is unlinked and private to this process, and its length is not
+        // changed while mapped. The server only reads from it.
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        let seg = conn.generate_id()?;
+        conn.shm_attach_fd(seg, OwnedFd::from(file), true)?
+            .check()?;
+        Ok(Self { seg, map })
+    }
+}
@@ -0,0 +1,84 @@
+//! Connection to the X server, opened once and shared by the beacon windows.
+
+use log::debug;
+#[cfg(feature = "native-x11")]
+use std::cell::OnceCell;
+use std::rc::Rc;
+use x11rb::connection::Connection;
+#[cfg(feature = "native-x11")]
+use x11rb::protocol::xproto::{ColormapAlloc, ConnectionExt as _, VisualClass};
+use x11rb::rust_connection::RustConnection;
+
+/// Depth of an ARGB visual
+#[cfg(feature = "native-x11")]
+pub const ARGB_DEPTH: u8 = 32;
+
+/// 32-bit TrueColor visual with a colormap for it.
+#[cfg(feature = "native-x11")]
+#[derive(Debug, Clone, Copy)]
+pub struct ArgbVisual {
+    pub visual: u32,
+    pub colormap: u32,
+}
+
+pub struct XServer {
+    conn: RustConnection,
+    screen_num: usize,
+    #[cfg(feature = "native-x11")]
+    argb_visual: OnceCell<ArgbVisual>,
+}
+
+impl XServer {
+    pub fn connect() -> Result<Rc<Self>, x11rb::errors::ConnectError> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        Ok(Rc::new(Self {
+            conn,
+            screen_num,
+            #[cfg(feature = "native-x11")]
+            argb_visual: OnceCell::new(),
+        }))
+    }
+
+    pub fn conn(&self) -> &RustConnection {
+        &self.conn
+    }
+
+    pub fn screen_num(&self) -> usize {
+        self.screen_num
+    }
+
+    /// Drops queued events and errors of unchecked requests, which nobody waits for
+    /// but which would pile up on the long-lived connection.
+    pub fn discard_events(&self) {
+        while let Ok(Some(event)) = self.conn.poll_for_event() {
+            debug!("Discard X event: {:?}", event);
+        }
+    }
+}
+
+#[cfg(feature = "native-x11")]
+impl XServer {
+    /// The ARGB visual of the screen, looked up and given a colormap on first use.
+    pub fn argb_visual(&self) -> Result<ArgbVisual, Box<dyn std::error::Error>> {
+        if let Some(argb_visual) = self.argb_visual.get() {
+            return Ok(*argb_visual);
+        }
+
+        let screen = &self.conn.setup().roots[self.screen_num];
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .filter(|depth| depth.depth == ARGB_DEPTH)
+            .flat_map(|depth| &depth.visuals)
+            .find(|visual| visual.class == VisualClass::TRUE_COLOR)
+            .ok_or("no 32-bit ARGB visual")?
+            .visual_id;
+
+        let colormap = self.conn.generate_id()?;
+        self.conn
+            .create_colormap(ColormapAlloc::NONE, colormap, screen.root, visual)?;
+        Ok(*self
+            .argb_visual
+            .get_or_init(|| ArgbVisual { visual, colormap }))
+    }
+}
@@ -0,0 +1,154 @@
+//! Clicks through a running beacon onto the window underneath, on a private Xvfb display.
+//!
+//! Needs `Xvfb`, so it is ignored by default: run it with `cargo test -- --ignored`.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use x11rb::COPY_DEPTH_FROM_PARENT;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{
+    BUTTON_PRESS_EVENT, ConnectionExt, CreateWindowAux, EventMask, MapState, WindowClass,
+};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+
+const SCREEN_SIZE: (u16, u16) = (640, 480);
+const CURSOR: (i16, i16) = (100, 100);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Kills the process when dropped, so a failing test does not leave it behind.
+struct Process(Child);
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts Xvfb on a free display and returns it with its name.
+fn start_xvfb() -> (Process, String) {
+    let screen = format!("{}x{}x24", SCREEN_SIZE.0, SCREEN_SIZE.1);
+    let mut child = Command::new("Xvfb")
+        .args([
+            "-displayfd",
+            "1",
+            "-screen",
+            "0",
+            &screen,
+            "-nolisten",
+            "tcp",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("cannot start Xvfb");
+
+    // Xvfb writes the display number once it accepts connections.
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let display = format!(":{}", line.trim());
+    (Process(child), display)
+}
+
+/// Polls `condition` until it holds or `TIMEOUT` passes.
+fn wait_for(mut condition: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+fn viewable_children(conn: &RustConnection, root: u32) -> Vec<u32> {
+    let children = conn.query_tree(root).unwrap().reply().unwrap().children;
+    children
+        .into_iter()
+        .filter(|&window| {
+            let attributes = conn.get_window_attributes(window).unwrap().reply().unwrap();
+            attributes.map_state == MapState::VIEWABLE
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "needs Xvfb; run with `cargo test -- --ignored`"]
+fn click_passes_through_beacon() {
+    let (_xvfb, display) = start_xvfb();
+
+    let (conn, screen_num) = x11rb::connect(Some(&display)).unwrap();
+    let root = conn.setup().roots[screen_num].root;
+
+    // A window covering the whole screen, standing in for the application under the cursor.
+    let target = conn.generate_id().unwrap();
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        target,
+        root,
+        0,
+        0,
+        SCREEN_SIZE.0,
+        SCREEN_SIZE.1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        0,
+        &CreateWindowAux::new().event_mask(EventMask::BUTTON_PRESS),
+    )
+    .unwrap();
+    conn.map_window(target).unwrap();
+    conn.warp_pointer(x11rb::NONE, root, 0, 0, 0, 0, CURSOR.0, CURSOR.1)
+        .unwrap();
+    conn.flush().unwrap();
+    assert!(wait_for(|| viewable_children(&conn, root) == [target]));
+
+    // Keep the beacon up long enough, isolated from the user's configuration and instance.
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("click_through");
+    std::fs::create_dir_all(&home).unwrap();
+    let script = home.join("cursor.txt");
+    std::fs::write(&script, format!("{} {}\n", CURSOR.0, CURSOR.1)).unwrap();
+    let _beacon = Process(
+        Command::new(env!("CARGO_BIN_EXE_cursor-beacon"))
+            .args(["--radius", "100", "--duration", "10000"])
+            // Unshaped and filled, so the click lands on the window itself: only its empty
+            // input region can let it through.
+            .args(["--transparency", "composite", "--shape", "disc"])
+            .arg("--cursor-source=script")
+            .arg("--cursor-script")
+            .arg(&script)
+            .env("DISPLAY", &display)
+            .env("XDG_CONFIG_HOME", &home)
+            .env("XDG_RUNTIME_DIR", &home)
+            .spawn()
+            .unwrap(),
+    );
+    assert!(
+        wait_for(|| viewable_children(&conn, root).len() == 2),
+        "beacon window did not appear"
+    );
+    // The input region is set right after the window is mapped.
+    std::thread::sleep(Duration::from_millis(200));
+
+    conn.xtest_fake_input(BUTTON_PRESS_EVENT, 1, 0, root, CURSOR.0, CURSOR.1, 0)
+        .unwrap();
+    conn.flush().unwrap();
+
+    let clicked = wait_for(|| {
+        while let Some(event) = conn.poll_for_event().unwrap() {
+            if let Event::ButtonPress(press) = event
+                && press.event == target
+            {
+                return true;
+            }
+        }
+        false
+    });
+    assert!(clicked, "the beacon swallowed the click");
+}